@@ -1,15 +1,17 @@
 use actix_web::http::StatusCode;
 use actix_web::web::{Data, Json};
 use actix_web::{HttpRequest, HttpResponse};
-use log::{info, warn};
-use rand::RngCore;
+use chrono::Utc;
+use log::{error, info, warn};
 use subtle::ConstantTimeEq;
 
-use std::fs;
-use std::path::Path;
-use std::sync::{RwLock, RwLockWriteGuard};
+use std::sync::RwLock;
 
-use crate::animal_facts::{Fact, FactLists};
+use crate::animal_facts::{normalize_tag, valid_weight, Fact, Revision, TrashedFact};
+use crate::duplicates::{DuplicateAction, DuplicateChecker, SimilarResp};
+use crate::language::normalize_language;
+use crate::reload::reload_facts;
+use crate::storage::{FactChange, FlagChange};
 use crate::*;
 
 fn check_admin_perms<'a>(
@@ -24,7 +26,15 @@ fn check_admin_perms<'a>(
         {
             Some(admin) => {
                 let perms = admin.permissions;
-                if perms.add_fact || perms.delete_fact || perms.view_flags || perms.delete_flag {
+                if perms.add_fact
+                    || perms.delete_fact
+                    || perms.edit_fact
+                    || perms.manage_trash
+                    || perms.reload
+                    || perms.backup
+                    || perms.view_flags
+                    || perms.delete_flag
+                {
                     Some((admin, Some(admin.permissions)))
                 } else {
                     Some((admin, None))
@@ -47,7 +57,7 @@ fn check_user<'a>(
             // Check if they are allowed to perform the desired action
             let missing_perms_resp = generate_response(&RESP_MISSING_PERMS);
             match action {
-                AdminAction::View => {
+                AdminAction::View | AdminAction::History | AdminAction::Duplicates => {
                     if !perms.view_facts {
                         return Err(missing_perms_resp);
                     }
@@ -62,6 +72,32 @@ fn check_user<'a>(
                         return Err(missing_perms_resp);
                     }
                 }
+                AdminAction::Update
+                | AdminAction::Revert
+                | AdminAction::Weigh
+                | AdminAction::Tag
+                | AdminAction::Untag
+                | AdminAction::SetTranslation
+                | AdminAction::DeleteTranslation => {
+                    if !perms.edit_fact {
+                        return Err(missing_perms_resp);
+                    }
+                }
+                AdminAction::ViewTrash | AdminAction::Restore | AdminAction::EmptyTrash => {
+                    if !perms.manage_trash {
+                        return Err(missing_perms_resp);
+                    }
+                }
+                AdminAction::Reload => {
+                    if !perms.reload {
+                        return Err(missing_perms_resp);
+                    }
+                }
+                AdminAction::Export | AdminAction::RestoreBundle => {
+                    if !perms.backup {
+                        return Err(missing_perms_resp);
+                    }
+                }
             }
             // Validated for performing their action
             Ok(user)
@@ -89,89 +125,323 @@ pub fn modify_fact(
         Err(resp) => return resp,
     };
 
-    // Check if the requested animal list is loaded
-    match body.animal_type {
-        Animal::Cat => {
-            if state.fact_lists.cat_facts.is_none() {
-                return generate_response(&RESP_NOT_LOADED);
-            }
+    // Check if the requested animal exists and its list is loaded
+    let animal = match state.config.animal_type(&body.animal_type) {
+        Some(animal) => animal,
+        None => return generate_response(&RESP_UNKNOWN_ANIMAL),
+    };
+    if state.fact_lists.get(&animal.slug).is_none() {
+        return generate_response(&RESP_NOT_LOADED);
+    }
+
+    match action {
+        AdminAction::Add => add_fact(animal, user, body.into_inner(), &state),
+        AdminAction::Delete => delete_fact(animal, user, body.into_inner(), &state),
+        AdminAction::Update => update_fact(animal, user, body.into_inner(), &state),
+        AdminAction::Tag | AdminAction::Untag => {
+            tag_fact(action, animal, user, body.into_inner(), &state)
         }
-        Animal::Dog => {
-            if state.fact_lists.dog_facts.is_none() {
-                return generate_response(&RESP_NOT_LOADED);
-            }
+        AdminAction::SetTranslation | AdminAction::DeleteTranslation => {
+            translate_fact(action, animal, user, body.into_inner(), &state)
+        }
+        AdminAction::ViewTrash => view_trash(animal, &state),
+        AdminAction::Restore => restore_fact(animal, user, body.into_inner(), &state),
+        AdminAction::EmptyTrash => empty_trash(animal, user, &state),
+        AdminAction::History => view_history(animal, body.into_inner(), &state),
+        AdminAction::Revert => revert_fact(animal, user, body.into_inner(), &state),
+        AdminAction::Weigh => weigh_fact(animal, user, body.into_inner(), &state),
+        AdminAction::Duplicates => view_duplicates(animal, &state),
+        AdminAction::View => view_facts(animal, &state),
+        // These have their own routes, since they aren't for a single animal
+        AdminAction::Reload | AdminAction::Export | AdminAction::RestoreBundle => {
+            generate_response(&RESP_NOT_LOADED)
         }
     }
+}
 
-    match action {
-        AdminAction::Add => add_fact(body.animal_type, user, body.into_inner(), &state),
-        AdminAction::Delete => delete_fact(body.animal_type, user, body.into_inner(), &state),
-        AdminAction::View => view_facts(body.animal_type, &state),
+pub fn reload(state: Data<APIState>, body: Json<AdminReloadRequest>) -> HttpResponse {
+    let user = match check_user(AdminAction::Reload, &body.key, &state) {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    match reload_facts(&state) {
+        Ok(summary) => {
+            warn!("Facts reloaded by {}", user.name);
+            state.json_response(&summary)
+        }
+        Err(errors) => {
+            let message = format!(
+                "The reloaded data was invalid, so nothing was changed: {}",
+                errors.join("; ")
+            );
+            warn!("{} tried to reload the facts. {}", user.name, message);
+            generate_response(&JsonResp::with_message(500, message.into()))
+        }
+    }
+}
+
+// Adds many facts at once from a JSON, CSV, or text file. Facts that are already present are skipped,
+// and an animal that has no facts loaded yet is loaded by its first import.
+pub fn import_facts(state: Data<APIState>, body: Json<AdminImportRequest>) -> HttpResponse {
+    let user = match check_user(AdminAction::Add, &body.key, &state) {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let animal = match state.config.animal_type(&body.animal_type) {
+        Some(animal) => animal,
+        None => return generate_response(&RESP_UNKNOWN_ANIMAL),
+    };
+    let (fact_list, trash) = match state.fact_lists.get_unloaded(&animal.slug) {
+        Some(lists) => lists,
+        None => return generate_response(&RESP_NOT_LOADED),
+    };
+
+    let content_field = body
+        .content_field
+        .as_deref()
+        .unwrap_or(import::DEFAULT_CONTENT_FIELD);
+    let entries = match import::parse(&body.data, body.format, content_field) {
+        Ok(entries) => entries,
+        Err(message) => return generate_response(&JsonResp::with_message(400, message.into())),
+    };
+
+    let mut list_lock = fact_list.write().unwrap();
+    let trash_lock = trash.read().unwrap();
+    let (added, report) = import::new_facts(
+        &list_lock,
+        &trash_lock,
+        entries,
+        Some(&user.name),
+        &state.config,
+    );
+
+    if !added.is_empty() {
+        let old_len = list_lock.len();
+        list_lock.extend(added.iter().cloned());
+        if let Err(resp) = modify_persistent_fact(
+            animal,
+            &list_lock,
+            &trash_lock,
+            FactChange::Imported(added.clone()),
+            &state,
+        ) {
+            list_lock.truncate(old_len);
+            return resp;
+        }
+        state.fact_lists.set_loaded(animal.slug.clone(), true);
+
+        let mut index = state.search_index.write().unwrap();
+        for fact in &added {
+            index.insert(&animal.slug, fact);
+        }
     }
+
+    warn!(
+        "{} {} facts imported by {}, with {} skipped and {} rejected",
+        report.added, animal.name, user.name, report.skipped, report.rejected
+    );
+
+    state.json_response(&report)
 }
 
-fn view_facts(animal: Animal, state: &APIState) -> HttpResponse {
-    // Unwrap is already verified before function call
-    let FactLists {
-        cat_facts,
-        dog_facts,
-    } = &state.fact_lists;
-    let fact_list = match animal {
-        Animal::Cat => cat_facts.as_ref().unwrap().read().unwrap(),
-        Animal::Dog => dog_facts.as_ref().unwrap().read().unwrap(),
+pub fn export(state: Data<APIState>, body: Json<AdminExportRequest>) -> HttpResponse {
+    let user = match check_user(AdminAction::Export, &body.key, &state) {
+        Ok(user) => user,
+        Err(resp) => return resp,
     };
 
-    HttpResponse::Ok().status(StatusCode::OK).json(&*fact_list)
+    match bundle::export_state(&state) {
+        Ok(bundle) => {
+            warn!("Everything was exported by {}", user.name);
+            state.json_response(&bundle)
+        }
+        Err(err) => {
+            error!("Failed loading the flags to export: {}", err);
+            generate_response(&JsonResp::new(500, "The flags couldn't be loaded"))
+        }
+    }
+}
+
+// Restores an exported bundle, either replacing what's there or merging into it
+pub fn restore_bundle(state: Data<APIState>, body: Json<AdminRestoreRequest>) -> HttpResponse {
+    let user = match check_user(AdminAction::RestoreBundle, &body.key, &state) {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    match bundle::restore_state(&state, &body.bundle, body.mode) {
+        Ok(report) => {
+            warn!(
+                "A bundle exported at {} was restored by {}",
+                body.bundle.exported_at, user.name
+            );
+            state.json_response(&report)
+        }
+        Err(bundle::RestoreError::Invalid(message)) => {
+            generate_response(&JsonResp::with_message(400, message.into()))
+        }
+        Err(err) => {
+            error!("Failed restoring a bundle: {}", err);
+            generate_response(&RESP_SAVE_FAILED)
+        }
+    }
+}
+
+fn view_facts(animal: &AnimalType, state: &APIState) -> HttpResponse {
+    let fact_list = determine_list(animal, state).read().unwrap();
+
+    state.json_response(&*fact_list)
 }
 
 fn add_fact(
-    animal: Animal,
+    animal: &AnimalType,
     user: &Admin,
     request: AdminFactRequest,
     state: &APIState,
 ) -> HttpResponse {
-    let id = rand::thread_rng().next_u64();
-
-    let fact_list = determine_list(animal, &state.fact_lists);
+    let fact_list = determine_list(animal, state);
     let mut list_lock = fact_list.write().unwrap();
 
-    match request.fact_content {
+    if let Some(weight) = request.fact_weight {
+        if !valid_weight(weight) {
+            return generate_response(&RESP_BAD_WEIGHT);
+        }
+    }
+
+    let similar_ids = match request.fact_content {
         Some(content) => {
-            list_lock.push(Fact { id, content });
+            let trash_lock = determine_trash(animal, state).read().unwrap();
+
+            let duplicates = &state.config.duplicates;
+            let similar_ids = DuplicateChecker::new(duplicates, list_lock.iter()).similar(&content);
+            if !similar_ids.is_empty() && duplicates.action == DuplicateAction::Reject {
+                warn!(
+                    "{} tried to add a {} fact similar to {:?}",
+                    user.name, animal.name, similar_ids
+                );
+                return similar_response(&RESP_TOO_SIMILAR, similar_ids, state);
+            }
+
+            let id = state
+                .config
+                .id_scheme
+                .next_id(&ids::fact_ids(&list_lock, &trash_lock));
+            let now = Utc::now();
+            let fact = Fact {
+                id,
+                content: content.clone(),
+                created_at: Some(now),
+                created_by: Some(user.name.clone()),
+                source: request
+                    .fact_source
+                    .filter(|source| !source.trim().is_empty()),
+                tags: normalize_tags(request.fact_tags.unwrap_or_default()),
+                history: vec![Revision {
+                    content,
+                    revised_at: Some(now),
+                    revised_by: Some(user.name.clone()),
+                }],
+                weight: request.fact_weight,
+                ..Default::default()
+            };
+            list_lock.push(fact.clone());
+
+            if let Err(resp) = modify_persistent_fact(
+                animal,
+                &list_lock,
+                &trash_lock,
+                FactChange::Added(fact.clone()),
+                state,
+            ) {
+                list_lock.pop();
+                return resp;
+            }
+
+            state
+                .search_index
+                .write()
+                .unwrap()
+                .insert(&animal.slug, &fact);
+
+            similar_ids
         }
         None => {
             return generate_response(&RESP_NO_CONTENT_SPECIFIED);
         }
+    };
+
+    let message = CreatedAction::Fact {
+        animal: &animal.name,
     }
+    .message();
+    warn!("{} by {}", message, user.name);
 
-    modify_persistent_fact(animal, list_lock, state);
+    let resp = JsonResp::with_message(201, message);
+    if similar_ids.is_empty() {
+        generate_response(&resp)
+    } else {
+        similar_response(&resp, similar_ids, state)
+    }
+}
 
-    let message = CreatedAction::Fact { animal };
-    warn!("{} by {}", message.as_str(), user.name);
+// A response listing the facts that a new one resembles
+fn similar_response(resp: &JsonResp, similar_ids: Vec<u64>, state: &APIState) -> HttpResponse {
+    let body = SimilarResp {
+        code: resp.code,
+        message: resp.message.clone(),
+        similar_ids,
+    };
+    state.json_response_with(StatusCode::from_u16(resp.code).unwrap(), &body)
+}
 
-    let resp = JsonResp::new(201, message.as_str());
-    generate_response(&resp)
+// Groups of facts that are similar to each other, using the same threshold as adding facts
+fn view_duplicates(animal: &AnimalType, state: &APIState) -> HttpResponse {
+    let list_lock = determine_list(animal, state).read().unwrap();
+
+    state.json_response(&duplicates::clusters(&list_lock, &state.config.duplicates))
 }
 
+// Deleted facts are moved to the trash instead of being lost, and can be restored from there
 fn delete_fact(
-    animal: Animal,
+    animal: &AnimalType,
     user: &Admin,
     request: AdminFactRequest,
     state: &APIState,
 ) -> HttpResponse {
     if let Some(rem_id) = request.fact_id {
-        let fact_list = determine_list(animal, &state.fact_lists);
+        let fact_list = determine_list(animal, state);
         let mut list_lock = fact_list.write().unwrap();
-        if let Some(found) = list_lock
-            .iter()
-            .enumerate()
-            .find(|(_, fact)| fact.id == rem_id)
-        {
-            let pos = found.0;
-            list_lock.remove(pos);
-            modify_persistent_fact(animal, list_lock, state);
+        if let Some(pos) = list_lock.iter().position(|fact| fact.id == rem_id) {
+            let fact = list_lock.remove(pos);
+            let trashed = TrashedFact {
+                fact,
+                deleted_at: Utc::now(),
+                deleted_by: user.name.clone(),
+            };
+            let mut trash_lock = determine_trash(animal, state).write().unwrap();
+            trash_lock.push(trashed.clone());
+
+            if let Err(resp) = modify_persistent_fact(
+                animal,
+                &list_lock,
+                &trash_lock,
+                FactChange::Trashed(trashed),
+                state,
+            ) {
+                let trashed = trash_lock.pop().unwrap();
+                list_lock.insert(pos, trashed.fact);
+                return resp;
+            }
+
+            state
+                .search_index
+                .write()
+                .unwrap()
+                .remove(&animal.slug, rem_id);
 
-            warn!("{} fact removed by {}", animal.as_str(), user.name);
+            warn!("{} fact #{} trashed by {}", animal.name, rem_id, user.name);
 
             HttpResponse::NoContent().finish()
         } else {
@@ -182,6 +452,376 @@ fn delete_fact(
     }
 }
 
+fn view_trash(animal: &AnimalType, state: &APIState) -> HttpResponse {
+    let trash = determine_trash(animal, state).read().unwrap();
+
+    state.json_response(&*trash)
+}
+
+fn restore_fact(
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let restore_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    let mut list_lock = determine_list(animal, state).write().unwrap();
+    let mut trash_lock = determine_trash(animal, state).write().unwrap();
+    if let Some(pos) = trash_lock
+        .iter()
+        .position(|trashed| trashed.fact.id == restore_id)
+    {
+        let trashed = trash_lock.remove(pos);
+        list_lock.push(trashed.fact.clone());
+
+        if let Err(resp) = modify_persistent_fact(
+            animal,
+            &list_lock,
+            &trash_lock,
+            FactChange::Restored(trashed.fact.clone()),
+            state,
+        ) {
+            list_lock.pop();
+            trash_lock.insert(pos, trashed);
+            return resp;
+        }
+
+        state
+            .search_index
+            .write()
+            .unwrap()
+            .insert(&animal.slug, &trashed.fact);
+
+        warn!(
+            "{} fact #{} restored by {}",
+            animal.name, restore_id, user.name
+        );
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+fn empty_trash(animal: &AnimalType, user: &Admin, state: &APIState) -> HttpResponse {
+    let list_lock = determine_list(animal, state).read().unwrap();
+    let mut trash_lock = determine_trash(animal, state).write().unwrap();
+    let emptied = std::mem::take(&mut *trash_lock);
+
+    if let Err(resp) = modify_persistent_fact(
+        animal,
+        &list_lock,
+        &trash_lock,
+        FactChange::TrashEmptied,
+        state,
+    ) {
+        *trash_lock = emptied;
+        return resp;
+    }
+
+    warn!(
+        "{} deleted {} facts permanently by emptying the {} trash",
+        user.name,
+        emptied.len(),
+        animal.name
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+fn update_fact(
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let edit_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    // Either the content, the source, or both can be changed at once
+    if request.fact_content.is_none() && request.fact_source.is_none() {
+        return generate_response(&RESP_NO_CONTENT_SPECIFIED);
+    }
+
+    let fact_list = determine_list(animal, state);
+    let mut list_lock = fact_list.write().unwrap();
+    if let Some(pos) = list_lock.iter().position(|fact| fact.id == edit_id) {
+        let original = list_lock[pos].clone();
+        let fact = &mut list_lock[pos];
+        // The ID is left untouched so that any flags on the fact still point to it
+        if let Some(content) = request.fact_content {
+            fact.revise(content, &user.name);
+        }
+        // An empty source removes the one the fact had
+        if let Some(source) = request.fact_source {
+            fact.source = Some(source).filter(|source| !source.trim().is_empty());
+        }
+        fact.updated_at = Some(Utc::now());
+        if let Err(resp) = save_edit(animal, &mut list_lock, pos, original, state) {
+            return resp;
+        }
+
+        warn!("{} fact #{} updated by {}", animal.name, edit_id, user.name);
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+fn view_history(animal: &AnimalType, request: AdminFactRequest, state: &APIState) -> HttpResponse {
+    let view_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    let list_lock = determine_list(animal, state).read().unwrap();
+    match list_lock.iter().find(|fact| fact.id == view_id) {
+        Some(fact) => state.json_response(&fact.revisions()),
+        None => generate_response(&RESP_ID_NOT_FOUND),
+    }
+}
+
+// Reverting adds the old content as a new revision, so the history itself is never rewritten
+fn revert_fact(
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let edit_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+    let revision = match request.revision {
+        Some(revision) => revision,
+        None => return generate_response(&RESP_NO_REVISION_SUPPLIED),
+    };
+
+    let fact_list = determine_list(animal, state);
+    let mut list_lock = fact_list.write().unwrap();
+    if let Some(pos) = list_lock.iter().position(|fact| fact.id == edit_id) {
+        let original = list_lock[pos].clone();
+        let fact = &mut list_lock[pos];
+        let content = match fact.revisions().into_iter().nth(revision) {
+            Some(old) => old.content,
+            None => return generate_response(&RESP_REVISION_NOT_FOUND),
+        };
+        fact.revise(content, &user.name);
+        if let Err(resp) = save_edit(animal, &mut list_lock, pos, original, state) {
+            return resp;
+        }
+
+        warn!(
+            "{} fact #{} reverted to revision {} by {}",
+            animal.name, edit_id, revision, user.name
+        );
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+fn tag_fact(
+    action: AdminAction,
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let edit_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    let tags = normalize_tags(request.fact_tags.unwrap_or_default());
+    if tags.is_empty() {
+        return generate_response(&RESP_NO_TAGS_SUPPLIED);
+    }
+
+    let fact_list = determine_list(animal, state);
+    let mut list_lock = fact_list.write().unwrap();
+    if let Some(pos) = list_lock.iter().position(|fact| fact.id == edit_id) {
+        let original = list_lock[pos].clone();
+        let fact = &mut list_lock[pos];
+        let direction = if action == AdminAction::Tag {
+            for tag in &tags {
+                if !fact.tags.contains(tag) {
+                    fact.tags.push(tag.clone());
+                }
+            }
+            "added to"
+        } else {
+            fact.tags.retain(|tag| !tags.contains(tag));
+            "removed from"
+        };
+        fact.updated_at = Some(Utc::now());
+        if let Err(resp) = save_edit(animal, &mut list_lock, pos, original, state) {
+            return resp;
+        }
+
+        warn!(
+            "Tags {:?} {} {} fact #{} by {}",
+            tags, direction, animal.name, edit_id, user.name
+        );
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+// Changes how likely a fact is to be picked, which doesn't count as a revision of its content
+fn weigh_fact(
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let edit_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    let weight = match request.fact_weight {
+        Some(weight) if valid_weight(weight) => weight,
+        _ => return generate_response(&RESP_BAD_WEIGHT),
+    };
+
+    let fact_list = determine_list(animal, state);
+    let mut list_lock = fact_list.write().unwrap();
+    if let Some(pos) = list_lock.iter().position(|fact| fact.id == edit_id) {
+        let original = list_lock[pos].clone();
+        let fact = &mut list_lock[pos];
+        fact.weight = Some(weight);
+        fact.updated_at = Some(Utc::now());
+        if let Err(resp) = save_edit(animal, &mut list_lock, pos, original, state) {
+            return resp;
+        }
+
+        warn!(
+            "{} fact #{} given a weight of {} by {}",
+            animal.name, edit_id, weight, user.name
+        );
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+fn translate_fact(
+    action: AdminAction,
+    animal: &AnimalType,
+    user: &Admin,
+    request: AdminFactRequest,
+    state: &APIState,
+) -> HttpResponse {
+    let edit_id = match request.fact_id {
+        Some(id) => id,
+        None => return generate_response(&RESP_NO_ID_SUPPLIED),
+    };
+
+    let language = match request.language.as_deref().map(normalize_language) {
+        Some(language) if !language.is_empty() => language,
+        _ => return generate_response(&RESP_NO_LANGUAGE_SUPPLIED),
+    };
+
+    // The base content is the default language, which is edited through `/admin/fact/update`
+    if language == state.config.default_language {
+        return generate_response(&RESP_DEFAULT_LANGUAGE);
+    }
+
+    let content = match (action, request.fact_content) {
+        (AdminAction::SetTranslation, Some(content)) => Some(content),
+        (AdminAction::SetTranslation, None) => {
+            return generate_response(&RESP_NO_CONTENT_SPECIFIED)
+        }
+        _ => None,
+    };
+
+    let fact_list = determine_list(animal, state);
+    let mut list_lock = fact_list.write().unwrap();
+    if let Some(pos) = list_lock.iter().position(|fact| fact.id == edit_id) {
+        let original = list_lock[pos].clone();
+        let fact = &mut list_lock[pos];
+        match content {
+            Some(content) => {
+                fact.translations.insert(language.clone(), content);
+            }
+            None => {
+                if fact.translations.remove(&language).is_none() {
+                    return generate_response(&RESP_NO_TRANSLATION);
+                }
+            }
+        }
+        fact.updated_at = Some(Utc::now());
+        if let Err(resp) = save_edit(animal, &mut list_lock, pos, original, state) {
+            return resp;
+        }
+
+        warn!(
+            "{} fact #{} had its '{}' translation {} by {}",
+            animal.name,
+            edit_id,
+            language,
+            if action == AdminAction::SetTranslation {
+                "set"
+            } else {
+                "deleted"
+            },
+            user.name
+        );
+
+        HttpResponse::NoContent().finish()
+    } else {
+        generate_response(&RESP_ID_NOT_FOUND)
+    }
+}
+
+// Saves an edit made to the fact at `pos`, putting back the original if it couldn't be saved
+fn save_edit(
+    animal: &AnimalType,
+    fact_list: &mut [Fact],
+    pos: usize,
+    original: Fact,
+    state: &APIState,
+) -> Result<(), HttpResponse> {
+    let change = FactChange::Updated(fact_list[pos].clone());
+
+    let trash_lock = determine_trash(animal, state).read().unwrap();
+    if let Err(resp) = modify_persistent_fact(animal, fact_list, &trash_lock, change, state) {
+        fact_list[pos] = original;
+        return Err(resp);
+    }
+
+    state
+        .search_index
+        .write()
+        .unwrap()
+        .insert(&animal.slug, &fact_list[pos]);
+    Ok(())
+}
+
+// Tags are stored lowercase and without duplicates so that filtering is predictable
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(&tag);
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
 pub fn modify_flag(
     state: Data<APIState>,
     req: HttpRequest,
@@ -225,13 +865,15 @@ pub fn modify_flag(
                 generate_response(&RESP_NO_ID_SUPPLIED)
             }
         }
+        // The rest only apply to facts, so flags have no routes for them
+        _ => generate_response(&RESP_NOT_LOADED),
     }
 }
 
 fn list_flags(state: &APIState) -> HttpResponse {
     let flag_list = state.fact_flags.as_ref().unwrap().read().unwrap();
 
-    HttpResponse::Ok().json(&*flag_list)
+    state.json_response(&*flag_list)
 }
 
 // This will allow an admin to add a flag and bypass the user-restricted method
@@ -241,32 +883,33 @@ fn add_flag(
     set_flag: (Animal, u64, Option<String>),
 ) -> HttpResponse {
     let flag_list = state.fact_flags.as_ref().unwrap();
-    let id = rand::thread_rng().next_u64();
 
-    {
-        let mut flag_list = flag_list.write().unwrap();
+    if !state.fact_exists(&set_flag.0, set_flag.1) {
+        return generate_response(&RESP_ID_NOT_FOUND);
+    }
 
-        if !flag_list
-            .iter()
-            .enumerate()
-            .any(|(_, flag)| flag.fact_id == set_flag.1)
-        {
-            return generate_response(&RESP_ID_NOT_FOUND);
-        }
+    let id = {
+        let mut flag_list = flag_list.write().unwrap();
+        let id = state.config.id_scheme.next_id(&ids::flag_ids(&flag_list));
 
-        flag_list.push(FactFlag {
+        let flag = FactFlag {
             id,
             fact_type: set_flag.0,
             fact_id: set_flag.1,
             reason: set_flag.2,
             flagger: user.name.clone(),
-        });
+        };
+        flag_list.push(flag.clone());
 
-        modify_persistent_flag(flag_list, state)
-    }
+        if let Err(resp) = modify_persistent_flag(&flag_list, FlagChange::Added(flag), state) {
+            flag_list.pop();
+            return resp;
+        }
+        id
+    };
 
     info!("Flag #{} added by {}", id, user.name);
-    let resp = JsonResp::new(201, CreatedAction::Flag.as_str());
+    let resp = JsonResp::with_message(201, CreatedAction::Flag.message());
     generate_response(&resp)
 }
 
@@ -280,7 +923,11 @@ fn delete_flag(state: &APIState, rem_id: u64, user: &Admin) -> HttpResponse {
         .find(|(_, flag)| flag.id == rem_id)
     {
         let pos = found.0;
-        list_lock.remove(pos);
+        let flag = list_lock.remove(pos);
+        if let Err(resp) = modify_persistent_flag(&list_lock, FlagChange::Removed(rem_id), state) {
+            list_lock.insert(pos, flag);
+            return resp;
+        }
 
         info!("Flag #{} removed by {}", rem_id, user.name);
 
@@ -291,35 +938,75 @@ fn delete_flag(state: &APIState, rem_id: u64, user: &Admin) -> HttpResponse {
 }
 
 fn determine_action(path: &str) -> AdminAction {
-    if path.ends_with("list") {
+    // The tag, translation, and trash routes are checked first since they also end in an action name
+    if path.ends_with("trash/list") {
+        AdminAction::ViewTrash
+    } else if path.ends_with("trash/restore") {
+        AdminAction::Restore
+    } else if path.ends_with("trash/empty") {
+        AdminAction::EmptyTrash
+    } else if path.ends_with("tag/add") {
+        AdminAction::Tag
+    } else if path.ends_with("tag/remove") {
+        AdminAction::Untag
+    } else if path.ends_with("translation/set") {
+        AdminAction::SetTranslation
+    } else if path.ends_with("translation/delete") {
+        AdminAction::DeleteTranslation
+    } else if path.ends_with("list") {
         AdminAction::View
     } else if path.ends_with("delete") {
         AdminAction::Delete
+    } else if path.ends_with("update") {
+        AdminAction::Update
+    } else if path.ends_with("history") {
+        AdminAction::History
+    } else if path.ends_with("revert") {
+        AdminAction::Revert
+    } else if path.ends_with("weight") {
+        AdminAction::Weigh
+    } else if path.ends_with("duplicates") {
+        AdminAction::Duplicates
     } else {
         AdminAction::Add
     }
 }
 
-fn determine_list(animal: Animal, fact_lists: &FactLists) -> &RwLock<Vec<Fact>> {
-    match animal {
-        // These unwraps are safe due to previous checks
-        Animal::Cat => fact_lists.cat_facts.as_ref().unwrap(),
-        Animal::Dog => fact_lists.dog_facts.as_ref().unwrap(),
-    }
+fn determine_list<'a>(animal: &AnimalType, state: &'a APIState) -> &'a RwLock<Vec<Fact>> {
+    // This unwrap is safe due to previous checks
+    state.fact_lists.get(&animal.slug).unwrap()
+}
+
+fn determine_trash<'a>(animal: &AnimalType, state: &'a APIState) -> &'a RwLock<Vec<TrashedFact>> {
+    // Every loaded list has a trash, so this is safe for the same reason
+    state.fact_lists.get_trash(&animal.slug).unwrap()
 }
 
 fn modify_persistent_fact(
-    animal: Animal,
-    fact_list: RwLockWriteGuard<Vec<Fact>>,
+    animal: &AnimalType,
+    fact_list: &[Fact],
+    trash: &[TrashedFact],
+    change: FactChange,
     state: &APIState,
-) {
-    let path = animal.get_filepath(&state.config.facts_dir);
-    fs::write(path, serde_json::to_string_pretty(&*fact_list).unwrap()).unwrap()
+) -> Result<(), HttpResponse> {
+    state
+        .storage
+        .save_facts(animal, fact_list, trash, &change)
+        .map_err(|err| {
+            error!("Failed saving the {} facts: {}", animal.name, err);
+            generate_response(&RESP_SAVE_FAILED)
+        })
 }
 
-fn modify_persistent_flag(flag_list: RwLockWriteGuard<Vec<FactFlag>>, state: &APIState) {
-    let path = Path::new(&state.config.facts_dir).join("fact_flags.json");
-    fs::write(path, serde_json::to_string_pretty(&*flag_list).unwrap()).unwrap()
+pub(crate) fn modify_persistent_flag(
+    flag_list: &[FactFlag],
+    change: FlagChange,
+    state: &APIState,
+) -> Result<(), HttpResponse> {
+    state.storage.save_flags(flag_list, &change).map_err(|err| {
+        error!("Failed saving the fact flags: {}", err);
+        generate_response(&RESP_SAVE_FAILED)
+    })
 }
 
 #[cfg(test)]
@@ -335,6 +1022,10 @@ mod permission_tests {
                 view_facts: true,
                 add_fact: true,
                 delete_fact: false,
+                edit_fact: false,
+                manage_trash: false,
+                reload: false,
+                backup: false,
                 view_flags: true,
                 add_flag: true,
                 delete_flag: false,
@@ -350,6 +1041,10 @@ mod permission_tests {
                 view_facts: false,
                 add_fact: false,
                 delete_fact: false,
+                edit_fact: false,
+                manage_trash: false,
+                reload: false,
+                backup: false,
                 view_flags: false,
                 add_flag: false,
                 delete_flag: false,
@@ -365,6 +1060,10 @@ mod permission_tests {
                 view_facts: true,
                 add_fact: true,
                 delete_fact: true,
+                edit_fact: true,
+                manage_trash: true,
+                reload: true,
+                backup: true,
                 view_flags: true,
                 add_flag: true,
                 delete_flag: true,
@@ -402,6 +1101,10 @@ mod permission_tests {
                 view_facts: true,
                 add_fact: true,
                 delete_fact: false,
+                edit_fact: false,
+                manage_trash: false,
+                reload: false,
+                backup: false,
                 view_flags: true,
                 add_flag: true,
                 delete_flag: false,
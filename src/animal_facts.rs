@@ -1,57 +1,646 @@
-use actix_web::web::{Data, HttpResponse};
-use rand::{seq::SliceRandom, thread_rng};
-use serde::{Deserialize, Serialize};
-use std::sync::RwLock;
+use actix_web::web::{Data, HttpResponse, Path, Query};
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{RwLock, RwLockReadGuard};
 
-use crate::{generate_response, APIState, Animal, RESP_NOT_LOADED};
+use crate::ids::MAX_SAFE_ID;
+use crate::language::{localize, requested_languages};
+use crate::shuffle::MAX_CLIENT_ID_LEN;
+use crate::{
+    generate_response, APIState, Animal, AnimalType, RESP_BAD_COUNT, RESP_ID_NOT_FOUND,
+    RESP_NOT_LOADED, RESP_NO_MATCHING_FACTS, RESP_UNKNOWN_ANIMAL,
+};
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
 pub struct Fact {
+    #[serde(deserialize_with = "crate::ids::deserialize_id")]
     pub id: u64,
     pub content: String,
+    // The metadata is optional since facts added before it existed won't have any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    // A URL or citation for where the fact came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // Categories the fact belongs to, ex. `anatomy` or `history`. Always stored normalized
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // Translations of `content` keyed by their normalized language tag, ex. `de` or `pt-br`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, String>,
+    // Every version of `content` the fact has had, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Revision>,
+    // How likely the fact is to be picked compared to others, where facts without one count as `DEFAULT_WEIGHT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+pub const DEFAULT_WEIGHT: f64 = 1.0;
+
+// Weights can be anything from 0, which means the fact is never picked at random, upwards
+pub fn valid_weight(weight: f64) -> bool {
+    weight.is_finite() && weight >= 0.0
+}
+
+// `flagged_weight` is raised to the number of flags, so anything outside of 0 to 1 would make flagged
+// facts more likely, or give them negative weights
+pub fn deserialize_flagged_weight<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f64, D::Error> {
+    let weight = f64::deserialize(deserializer)?;
+    if valid_weight(weight) && weight <= 1.0 {
+        Ok(weight)
+    } else {
+        Err(D::Error::custom(format!(
+            "flagged_weight must be from 0 to 1, not {}",
+            weight
+        )))
+    }
+}
+
+impl Fact {
+    // A fact with nothing but its content, which the other fields can be filled in on top of
+    pub fn new(id: u64, content: &str) -> Self {
+        Fact {
+            id,
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&normalize_tag(tag))
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or(DEFAULT_WEIGHT)
+    }
+
+    // Facts from before history was kept start with their current content as the first revision
+    pub fn revisions(&self) -> Vec<Revision> {
+        if self.history.is_empty() {
+            vec![Revision {
+                content: self.content.clone(),
+                revised_at: self.created_at,
+                revised_by: self.created_by.clone(),
+            }]
+        } else {
+            self.history.clone()
+        }
+    }
+
+    // Replaces the content of the fact, recording the change in its history
+    pub fn revise(&mut self, content: String, admin: &str) {
+        if self.history.is_empty() {
+            self.history = self.revisions();
+        }
+
+        let now = Utc::now();
+        self.history.push(Revision {
+            content: content.clone(),
+            revised_at: Some(now),
+            revised_by: Some(admin.to_string()),
+        });
+        self.content = content;
+        self.updated_at = Some(now);
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Revision {
+    pub content: String,
+    // These are only unknown for the original content of facts added before history was kept
+    pub revised_at: Option<DateTime<Utc>>,
+    pub revised_by: Option<String>,
+}
+
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
 }
 
-// The system can support all listed fact types, but they aren't required to be present
-#[derive(Serialize, Deserialize, Debug, Default)]
+// A deleted fact, which is kept until the trash is emptied so it can be restored
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TrashedFact {
+    #[serde(flatten)]
+    pub fact: Fact,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: String,
+}
+
+// An animal's facts and trash
+pub(crate) type AnimalLists = (Vec<Fact>, Vec<TrashedFact>);
+
+// What the public endpoints return, which leaves out who added the fact and when
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct PublicFact<'a> {
+    pub id: u64,
+    pub content: &'a str,
+    // The language `content` is in
+    pub lang: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub tags: &'a [String],
+}
+
+impl<'a> PublicFact<'a> {
+    pub fn localized(fact: &'a Fact, languages: &[String], default_language: &'a str) -> Self {
+        let (lang, content) = localize(fact, languages, default_language);
+
+        PublicFact {
+            id: fact.id,
+            content,
+            lang,
+            source: fact.source.as_deref(),
+            tags: &fact.tags,
+        }
+    }
+}
+
+// A random pick, along with the seed that picked it. Picks from a shuffle bag don't have one, since they can't be repeated
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct PickedFact<'a> {
+    #[serde(flatten)]
+    pub fact: PublicFact<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+// A pick across species, which says which animal it's about
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct AnimalFact<'a> {
+    pub animal: &'a Animal,
+    #[serde(flatten)]
+    pub fact: PickedFact<'a>,
+}
+
+// An animal's fact list along with its trash
+type ListLocks<'a> = (&'a RwLock<Vec<Fact>>, &'a RwLock<Vec<TrashedFact>>);
+
+// The system can support all configured fact types, but they aren't required to be present.
+// Every loaded list has a trash alongside it, which is always locked after the list itself.
+// When several lists are locked at once, they are locked in order of their animal.
+#[derive(Debug, Default)]
 pub struct FactLists {
-    pub cat_facts: Option<RwLock<Vec<Fact>>>,
-    pub dog_facts: Option<RwLock<Vec<Fact>>>,
+    lists: HashMap<Animal, RwLock<Vec<Fact>>>,
+    trash: HashMap<Animal, RwLock<Vec<TrashedFact>>>,
+    // Lists can exist without being loaded yet, so that a reload can fill them in later
+    loaded: RwLock<HashSet<Animal>>,
 }
 
-pub fn get_cat_fact(app_data: Data<APIState>) -> HttpResponse {
-    if let Some(fact_list) = &app_data.fact_lists.cat_facts {
-        let mut rng = thread_rng();
-        let list_lock = fact_list.read().unwrap();
+impl FactLists {
+    pub fn get(&self, animal: &Animal) -> Option<&RwLock<Vec<Fact>>> {
+        self.get_entry(animal).map(|(list, _)| list)
+    }
 
-        // This should never panic since `Some()` means there is >= 1 fact.
-        let rand_pick = list_lock.choose(&mut rng).unwrap();
+    pub fn get_trash(&self, animal: &Animal) -> Option<&RwLock<Vec<TrashedFact>>> {
+        self.get_entry(animal).map(|(_, trash)| trash)
+    }
 
-        app_data
-            .req_counter
-            .with_label_values(&[Animal::Cat.as_str()])
-            .inc();
+    fn get_entry(&self, animal: &Animal) -> Option<ListLocks<'_>> {
+        if self.loaded.read().unwrap().contains(animal) {
+            self.get_unloaded(animal)
+        } else {
+            None
+        }
+    }
 
-        HttpResponse::Ok().json(rand_pick)
-    } else {
-        generate_response(&RESP_NOT_LOADED)
+    // Gets an animal's lists even if they haven't been loaded
+    pub(crate) fn get_unloaded(&self, animal: &Animal) -> Option<ListLocks<'_>> {
+        Some((self.lists.get(animal)?, self.trash.get(animal)?))
+    }
+
+    pub fn insert(&mut self, animal: Animal, facts: Vec<Fact>, trash: Vec<TrashedFact>) {
+        self.insert_unloaded(animal.clone());
+        self.lists.insert(animal.clone(), RwLock::new(facts));
+        self.trash.insert(animal.clone(), RwLock::new(trash));
+        self.set_loaded(animal, true);
+    }
+
+    // Adds empty lists for an animal, which aren't served until they are loaded
+    pub fn insert_unloaded(&mut self, animal: Animal) {
+        self.lists.entry(animal.clone()).or_default();
+        self.trash.entry(animal).or_default();
+    }
+
+    pub(crate) fn set_loaded(&self, animal: Animal, loaded: bool) {
+        let mut loaded_lock = self.loaded.write().unwrap();
+        if loaded {
+            loaded_lock.insert(animal);
+        } else {
+            loaded_lock.remove(&animal);
+        }
+    }
+
+    pub fn remove(&mut self, animal: &Animal) -> Option<RwLock<Vec<Fact>>> {
+        self.set_loaded(animal.clone(), false);
+        self.trash.remove(animal);
+        self.lists.remove(animal)
     }
 }
 
-pub fn get_dog_fact(app_data: Data<APIState>) -> HttpResponse {
-    if let Some(fact_list) = &app_data.fact_lists.dog_facts {
-        let mut rng = thread_rng();
-        let list_lock = fact_list.read().unwrap();
+// Filters that narrow down which facts a random pick can choose from
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct FactQuery {
+    pub tag: Option<String>,
+    // Not a filter, but picks which translation of the fact is returned
+    pub lang: Option<String>,
+    // Also not a filter, but identifies the client for shuffled picks
+    pub client: Option<String>,
+    // Makes the picks the same every time for the same seed, as long as the matching facts don't change.
+    // Requests without one are given a new seed, which is returned so the pick can be shared.
+    pub seed: Option<u64>,
+}
 
-        // This should never panic since `Some()` means there is >= 1 fact.
-        let rand_pick = list_lock.choose(&mut rng).unwrap();
+impl FactQuery {
+    pub fn matches(&self, fact: &Fact) -> bool {
+        match &self.tag {
+            Some(tag) => fact.has_tag(tag),
+            None => true,
+        }
+    }
+}
 
-        app_data
-            .req_counter
-            .with_label_values(&[Animal::Dog.as_str()])
-            .inc();
+// How many facts the batch endpoint should return
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct CountQuery {
+    pub count: Option<usize>,
+}
 
-        HttpResponse::Ok().json(rand_pick)
-    } else {
-        generate_response(&RESP_NOT_LOADED)
+// Which animals a pick across species can come from, and how it's spread between them
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SpeciesQuery {
+    // A comma separated list of animals, ex. `cat,dog`. Every loaded animal is used when this isn't set
+    pub animals: Option<String>,
+    #[serde(default)]
+    pub weighting: Weighting,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    // Every fact has the same chance no matter its animal, so animals with more facts come up more
+    #[default]
+    Fact,
+    // Every animal has the same chance, and then a fact is picked from it
+    Species,
+}
+
+// Used by endpoints that return a specific fact, where filters don't apply
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct LanguageQuery {
+    pub lang: Option<String>,
+}
+
+// Resolves an animal from the URL into its config entry and loaded list
+pub(crate) fn find_list<'a>(
+    app_data: &'a APIState,
+    animal: &str,
+) -> Result<(&'a AnimalType, &'a RwLock<Vec<Fact>>), HttpResponse> {
+    let animal = Animal::new(animal);
+
+    let animal_type = match app_data.config.animal_type(&animal) {
+        Some(animal_type) => animal_type,
+        None => return Err(generate_response(&RESP_UNKNOWN_ANIMAL)),
+    };
+
+    match app_data.fact_lists.get(&animal) {
+        Some(fact_list) => Ok((animal_type, fact_list)),
+        None => Err(generate_response(&RESP_NOT_LOADED)),
+    }
+}
+
+// The client a request came from, by the `client` query parameter or else the configured header
+fn client_id<'a>(
+    app_data: &APIState,
+    req: &'a HttpRequest,
+    query: &'a FactQuery,
+) -> Option<&'a str> {
+    let header = || {
+        req.headers()
+            .get(app_data.config.shuffle.header.as_str())?
+            .to_str()
+            .ok()
+    };
+
+    query
+        .client
+        .as_deref()
+        .or_else(header)
+        .filter(|client| !client.is_empty() && client.len() <= MAX_CLIENT_ID_LEN)
+}
+
+// The seed a request's picks come from. Seeds that are made up stay within what JavaScript can hold
+// exactly, so clients can send them back as they got them.
+fn request_seed(query: &FactQuery) -> u64 {
+    query
+        .seed
+        .unwrap_or_else(|| thread_rng().gen_range(0..=MAX_SAFE_ID))
+}
+
+// Picks up to `count` different matching facts, along with the seed they came from. Clients that identify
+// themselves get them from their shuffle bag when that's enabled, unless they asked for a seed, and
+// everyone else gets them from a seed so the same picks can be made again.
+fn pick_facts<'a>(
+    app_data: &APIState,
+    req: &HttpRequest,
+    animal: &AnimalType,
+    query: &FactQuery,
+    matching: &[&'a Fact],
+    count: usize,
+) -> (Vec<&'a Fact>, Option<u64>) {
+    let mut weighted = weigh_facts(app_data, &animal.slug, matching);
+
+    if app_data.config.shuffle.enabled && query.seed.is_none() {
+        if let Some(client) = client_id(app_data, req, query) {
+            // Bags give every fact once no matter its weight, except for those that are never picked
+            let eligible: Vec<&Fact> = weighted.iter().map(|(fact, _)| *fact).collect();
+            let tag = query.tag.as_deref().map(normalize_tag);
+            let picks =
+                app_data
+                    .shuffle_bags
+                    .draw(client, &animal.slug, tag.as_deref(), &eligible, count);
+            return (picks, None);
+        }
     }
+
+    // Sorted by ID so the picks don't depend on how the facts happen to be stored
+    weighted.sort_by_key(|(fact, _)| fact.id);
+    let seed = request_seed(query);
+    let picks = choose_weighted(&weighted, &mut ChaCha8Rng::seed_from_u64(seed), count);
+    (picks, Some(seed))
+}
+
+fn choose_weighted<'a, R: Rng>(
+    weighted: &[(&'a Fact, f64)],
+    rng: &mut R,
+    count: usize,
+) -> Vec<&'a Fact> {
+    // The weights were all checked when weighing the facts, so this can't fail
+    weighted
+        .choose_multiple_weighted(rng, count, |(_, weight)| *weight)
+        .map(|picks| picks.map(|(fact, _)| *fact).collect())
+        .unwrap_or_default()
+}
+
+// Each fact's chance of being picked, leaving out facts that can't be. When `flagged_weight` is set,
+// the weight is multiplied by it once for every open flag on the fact.
+fn weigh_facts<'a>(
+    app_data: &APIState,
+    animal: &Animal,
+    matching: &[&'a Fact],
+) -> Vec<(&'a Fact, f64)> {
+    let mut flag_counts: HashMap<u64, i32> = HashMap::new();
+    if app_data.config.flagged_weight != 1.0 {
+        if let Some(flags) = &app_data.fact_flags {
+            for flag in flags.read().unwrap().iter() {
+                if flag.fact_type == *animal {
+                    *flag_counts.entry(flag.fact_id).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    matching
+        .iter()
+        .map(|fact| {
+            let flags = flag_counts.get(&fact.id).copied().unwrap_or(0);
+            (
+                *fact,
+                fact.weight() * app_data.config.flagged_weight.powi(flags),
+            )
+        })
+        .filter(|(_, weight)| valid_weight(*weight) && *weight > 0.0)
+        .collect()
+}
+
+pub fn get_fact(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    animal: Path<String>,
+    query: Query<FactQuery>,
+) -> HttpResponse {
+    let (animal_type, fact_list) = match find_list(&app_data, &animal) {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    let list_lock = fact_list.read().unwrap();
+
+    let matching: Vec<&Fact> = list_lock
+        .iter()
+        .filter(|fact| query.matches(fact))
+        .collect();
+    let (picked, seed) = pick_facts(&app_data, &req, animal_type, &query, &matching, 1);
+    let rand_pick = match picked.first() {
+        Some(fact) => *fact,
+        None => return generate_response(&RESP_NO_MATCHING_FACTS),
+    };
+
+    app_data
+        .req_counter
+        .with_label_values(&[&animal_type.name])
+        .inc();
+
+    let languages = requested_languages(&req, query.lang.as_deref());
+    app_data.json_response(&PickedFact {
+        fact: PublicFact::localized(rand_pick, &languages, &app_data.config.default_language),
+        seed,
+    })
+}
+
+pub fn get_facts(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    animal: Path<String>,
+    query: Query<FactQuery>,
+    count: Query<CountQuery>,
+) -> HttpResponse {
+    let (animal_type, fact_list) = match find_list(&app_data, &animal) {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    // Asking for more than the limit is fine, it just gets capped
+    let count = match count.count {
+        Some(0) => return generate_response(&RESP_BAD_COUNT),
+        Some(count) => count.min(app_data.config.max_batch_count),
+        None => 1,
+    };
+
+    let list_lock = fact_list.read().unwrap();
+
+    let matching: Vec<&Fact> = list_lock
+        .iter()
+        .filter(|fact| query.matches(fact))
+        .collect();
+
+    // If there aren't enough facts, every matching one is returned in a random order
+    let (picked, seed) = pick_facts(&app_data, &req, animal_type, &query, &matching, count);
+    if picked.is_empty() {
+        return generate_response(&RESP_NO_MATCHING_FACTS);
+    }
+
+    let languages = requested_languages(&req, query.lang.as_deref());
+    let picks: Vec<PickedFact> = picked
+        .into_iter()
+        .map(|fact| PickedFact {
+            fact: PublicFact::localized(fact, &languages, &app_data.config.default_language),
+            seed,
+        })
+        .collect();
+
+    // This counts requests, so a batch only counts once no matter its size
+    app_data
+        .req_counter
+        .with_label_values(&[&animal_type.name])
+        .inc();
+
+    app_data.json_response(&picks)
+}
+
+pub fn get_any_fact(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    query: Query<FactQuery>,
+    species: Query<SpeciesQuery>,
+) -> HttpResponse {
+    let requested: Vec<Animal> = species
+        .animals
+        .iter()
+        .flat_map(|animals| animals.split(','))
+        .map(str::trim)
+        .filter(|animal| !animal.is_empty())
+        .map(Animal::new)
+        .collect();
+    for animal in &requested {
+        if app_data.config.animal_type(animal).is_none() {
+            return generate_response(&RESP_UNKNOWN_ANIMAL);
+        }
+        if app_data.fact_lists.get(animal).is_none() {
+            return generate_response(&RESP_NOT_LOADED);
+        }
+    }
+
+    // Sorted so the lists are locked in the same order as everywhere else
+    let animals: BTreeSet<&Animal> = if requested.is_empty() {
+        app_data
+            .config
+            .animal_fact_types
+            .iter()
+            .map(|animal_type| &animal_type.slug)
+            .collect()
+    } else {
+        requested.iter().collect()
+    };
+    let list_locks: Vec<(&AnimalType, RwLockReadGuard<Vec<Fact>>)> = animals
+        .into_iter()
+        .filter_map(|animal| {
+            let animal_type = app_data.config.animal_type(animal)?;
+            let list = app_data.fact_lists.get(animal)?;
+            Some((animal_type, list.read().unwrap()))
+        })
+        .collect();
+
+    // Each list is sorted by ID so seeded picks don't depend on how the facts happen to be stored
+    let weighted: Vec<(&AnimalType, Vec<(&Fact, f64)>)> = list_locks
+        .iter()
+        .map(|(animal_type, list)| {
+            let matching: Vec<&Fact> = list.iter().filter(|fact| query.matches(fact)).collect();
+            let mut weighted = weigh_facts(&app_data, &animal_type.slug, &matching);
+            weighted.sort_by_key(|(fact, _)| fact.id);
+            (*animal_type, weighted)
+        })
+        .filter(|(_, weighted)| !weighted.is_empty())
+        .collect();
+
+    let seed = request_seed(&query);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let rand_pick = match species.weighting {
+        Weighting::Fact => {
+            let every_fact: Vec<(&AnimalType, &Fact, f64)> = weighted
+                .iter()
+                .flat_map(|(animal_type, weighted)| {
+                    weighted
+                        .iter()
+                        .map(move |(fact, weight)| (*animal_type, *fact, *weight))
+                })
+                .collect();
+            every_fact
+                .choose_weighted(&mut rng, |(_, _, weight)| *weight)
+                .ok()
+                .map(|(animal_type, fact, _)| (*animal_type, *fact))
+        }
+        Weighting::Species => weighted
+            .choose(&mut rng)
+            .and_then(|(animal_type, weighted)| {
+                let fact = *choose_weighted(weighted, &mut rng, 1).first()?;
+                Some((*animal_type, fact))
+            }),
+    };
+    let (animal_type, rand_pick) = match rand_pick {
+        Some(pick) => pick,
+        None => return generate_response(&RESP_NO_MATCHING_FACTS),
+    };
+
+    app_data
+        .req_counter
+        .with_label_values(&[&animal_type.name])
+        .inc();
+
+    let languages = requested_languages(&req, query.lang.as_deref());
+    app_data.json_response(&AnimalFact {
+        animal: &animal_type.slug,
+        fact: PickedFact {
+            fact: PublicFact::localized(rand_pick, &languages, &app_data.config.default_language),
+            seed: Some(seed),
+        },
+    })
+}
+
+pub fn get_fact_by_id(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    path: Path<(String, String)>,
+    query: Query<LanguageQuery>,
+) -> HttpResponse {
+    let (animal, id) = path.into_inner();
+
+    let (animal_type, fact_list) = match find_list(&app_data, &animal) {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    // Anything that isn't a number can't be an ID, so it's treated the same as a missing one
+    let id: u64 = match id.parse() {
+        Ok(id) => id,
+        Err(_) => return generate_response(&RESP_ID_NOT_FOUND),
+    };
+
+    let list_lock = fact_list.read().unwrap();
+    let fact = match list_lock.iter().find(|fact| fact.id == id) {
+        Some(fact) => fact,
+        None => return generate_response(&RESP_ID_NOT_FOUND),
+    };
+
+    app_data
+        .req_counter
+        .with_label_values(&[&animal_type.name])
+        .inc();
+
+    let languages = requested_languages(&req, query.lang.as_deref());
+    app_data.json_response(&PublicFact::localized(
+        fact,
+        &languages,
+        &app_data.config.default_language,
+    ))
 }
@@ -3,38 +3,144 @@
 
 use actix_web::{web, App, HttpServer};
 use flexi_logger::{Duplicate, Logger};
-use log::info;
-use prometheus::{Encoder, IntCounter, IntCounterVec, Opts, Registry, TextEncoder};
+use log::{error, info, warn};
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
 
 use std::fs;
-
+use std::path::Path;
+use std::{env, process};
+
+use animal_api::bundle::{self, Bundle, RestoreMode};
+use animal_api::import::{self, ImportFormat};
+use animal_api::search::SearchIndex;
+use animal_api::shuffle::{self, ShuffleBags};
+use animal_api::storage::StorageBackend;
 use animal_api::{
-    admin, animal_facts, flagging, load_fact_flags, load_fact_lists, APIState, Config,
+    load_fact_flags, load_fact_lists, reload, routes, storage, APIState, Animal, Config,
 };
 
-async fn prom_stats(app_data: web::Data<APIState>) -> String {
-    let register = &app_data.stat_register;
+use std::sync::RwLock;
+use std::time::Duration;
+
+// A subcommand of the binary, which runs in place of the server
+type Command = fn(&Config, &[String]) -> Result<(), String>;
+
+const IMPORT_USAGE: &str =
+    "Usage: animal_api import <animal> <file> [--format json|csv|text] [--field <content field>]";
+
+// Imports a file straight into storage, which refuses to run while the server is
+fn import_file(config: &Config, args: &[String]) -> Result<(), String> {
+    let (animal, file) = match args {
+        [animal, file, ..] => (Animal::from(animal.as_str()), Path::new(file)),
+        _ => return Err(IMPORT_USAGE.to_string()),
+    };
+
+    let mut format = ImportFormat::from_path(file);
+    let mut content_field = import::DEFAULT_CONTENT_FIELD.to_string();
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or(IMPORT_USAGE)?;
+        match option.as_str() {
+            "--format" => {
+                format = serde_json::from_value(serde_json::Value::String(value.clone()))
+                    .map_err(|_| format!("Unknown format `{}`\n{}", value, IMPORT_USAGE))?
+            }
+            "--field" => content_field = value.clone(),
+            _ => return Err(IMPORT_USAGE.to_string()),
+        }
+    }
+
+    let animal = config
+        .animal_type(&animal)
+        .ok_or_else(|| format!("`{}` isn't an animal in the config", animal))?;
+    let data = fs::read_to_string(file)
+        .map_err(|err| format!("{} couldn't be read: {}", file.display(), err))?;
+    let entries = import::parse(&data, format, &content_field)?;
+
+    let _lock = storage::lock(config).map_err(|err| format!("The import can't be run: {}", err))?;
+    let storage = storage::open(config)
+        .map_err(|err| format!("The storage backend couldn't be opened: {}", err))?;
+    let report = import::import_into_storage(storage.as_ref(), animal, entries, config)?;
+
+    println!(
+        "{} {} facts added, {} skipped, and {} rejected",
+        report.added, animal.name, report.skipped, report.rejected
+    );
+    for rejection in report.rejections {
+        println!("  {}", rejection);
+    }
+    for similar in report.similar {
+        let ids: Vec<String> = similar.similar_ids.iter().map(u64::to_string).collect();
+        println!(
+            "  Entry {} is similar to facts {}",
+            similar.entry,
+            ids.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// Writes everything in storage to a bundle file, for when the server isn't running
+fn export_bundle(config: &Config, args: &[String]) -> Result<(), String> {
+    let file = match args {
+        [file] => Path::new(file),
+        _ => return Err("Usage: animal_api export <file>".to_string()),
+    };
+
+    let storage = storage::open(config)
+        .map_err(|err| format!("The storage backend couldn't be opened: {}", err))?;
+    let bundle = bundle::export_storage(storage.as_ref(), config)
+        .map_err(|err| format!("The data couldn't be loaded: {}", err))?;
 
-    let mut buffer: Vec<u8> = Vec::with_capacity(100);
-    let encoder = TextEncoder::new();
+    let contents = serde_json::to_string_pretty(&bundle).unwrap();
+    fs::write(file, contents)
+        .map_err(|err| format!("{} couldn't be written: {}", file.display(), err))?;
 
-    let metrics = register.gather();
-    encoder.encode(&metrics, &mut buffer).unwrap();
-    String::from_utf8(buffer).unwrap()
+    println!(
+        "Exported {} facts, {} trashed facts, and {} flags",
+        bundle.counts.facts, bundle.counts.trash, bundle.counts.flags
+    );
+    Ok(())
 }
 
-async fn index() -> &'static str {
-    "Hello There! This is Gearbot's animal fact API. Head over to /cat/fact or /dog/fact to try it out!"
+const RESTORE_USAGE: &str = "Usage: animal_api restore <file> --mode replace|merge";
+
+// Restores a bundle file straight into storage, which refuses to run while the server is
+fn restore_bundle(config: &Config, args: &[String]) -> Result<(), String> {
+    let (file, mode) = match args {
+        [file, option, mode] if option == "--mode" => (Path::new(file), mode),
+        _ => return Err(RESTORE_USAGE.to_string()),
+    };
+    let mode: RestoreMode = serde_json::from_value(serde_json::Value::String(mode.clone()))
+        .map_err(|_| format!("Unknown mode `{}`\n{}", mode, RESTORE_USAGE))?;
+
+    let contents = fs::read_to_string(file)
+        .map_err(|err| format!("{} couldn't be read: {}", file.display(), err))?;
+    let bundle: Bundle = serde_json::from_str(&contents)
+        .map_err(|err| format!("{} isn't a bundle: {}", file.display(), err))?;
+
+    let _lock =
+        storage::lock(config).map_err(|err| format!("The restore can't be run: {}", err))?;
+    let storage = storage::open(config)
+        .map_err(|err| format!("The storage backend couldn't be opened: {}", err))?;
+    let report = bundle::restore_storage(storage.as_ref(), config, &bundle, mode)
+        .map_err(|err| err.to_string())?;
+
+    println!(
+        "Restored {} facts, {} trashed facts, and {} flags, with {} skipped",
+        report.facts, report.trash, report.flags, report.skipped
+    );
+    Ok(())
 }
 
 #[actix_web::main]
 async fn main() {
-    let fact_count = IntCounterVec::new(
+    let fact_count = IntGaugeVec::new(
         Opts::new("fact_count", "How many animal facts are currently loaded"),
         &["animal"],
     )
     .unwrap();
-    let flag_count = IntCounter::new("flag_count", "How many facts have been flagged").unwrap();
+    let flag_count = IntGauge::new("flag_count", "How many facts have been flagged").unwrap();
     let req_count = IntCounterVec::new(
         Opts::new("api_request_count", "How many requests we have served"),
         &["animal"],
@@ -52,6 +158,21 @@ async fn main() {
         toml::from_str(&config_string).expect("The config was malformed!")
     };
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command: Option<Command> = match args.first().map(String::as_str) {
+        Some("import") => Some(import_file),
+        Some("export") => Some(export_bundle),
+        Some("restore") => Some(restore_bundle),
+        _ => None,
+    };
+    if let Some(command) = command {
+        if let Err(message) = command(&config, &args[1..]) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        return;
+    }
+
     Logger::try_with_str(&config.logging_level)
         .expect("invalid logging level")
         .log_to_file(flexi_logger::FileSpec::default().directory(config.logging_dir.clone()))
@@ -59,8 +180,13 @@ async fn main() {
         .start()
         .unwrap();
 
-    let loaded_lists = load_fact_lists(&fact_count, &config);
-    let flags = load_fact_flags(&flag_count, &config);
+    // Kept until the server stops, so the import and restore commands can't change storage under it
+    let _lock = storage::lock(&config).expect("The storage couldn't be locked!");
+    let storage = storage::open(&config).expect("The storage backend couldn't be opened!");
+    let loaded_lists = load_fact_lists(&fact_count, &config, storage.as_ref());
+    let flags = load_fact_flags(&flag_count, &config, storage.as_ref());
+    let search_index = SearchIndex::new(&loaded_lists, &config);
+    let shuffle_bags = ShuffleBags::new(&config.shuffle);
 
     let server_binding = (config.server.ip, config.server.port);
 
@@ -68,30 +194,47 @@ async fn main() {
         config,
         fact_lists: loaded_lists,
         fact_flags: flags,
+        search_index: RwLock::new(search_index),
+        shuffle_bags,
+        storage,
         stat_register: reg,
         req_counter: req_count,
+        fact_count,
+        flag_count,
     });
 
+    // The database is the only thing read once the JSON files have been imported, so there's nothing to watch
+    if state_data.config.watch_interval > 0 {
+        if state_data.config.storage.backend == StorageBackend::Sqlite {
+            warn!("The facts directory isn't watched with the sqlite backend, since edits to the JSON files are never loaded");
+        } else {
+            let interval = Duration::from_secs(state_data.config.watch_interval);
+            reload::watch_facts_dir(state_data.clone(), interval);
+        }
+    }
+    #[cfg(unix)]
+    reload::reload_on_hangup(state_data.clone());
+    let shuffle_config = &state_data.config.shuffle;
+    if shuffle_config.enabled && shuffle_config.file.is_some() && shuffle_config.save_interval > 0 {
+        let interval = Duration::from_secs(shuffle_config.save_interval);
+        shuffle::save_periodically(state_data.clone(), interval);
+    }
+
     info!("Facts and configs loaded, starting server...");
 
+    let shutdown_data = state_data.clone();
     HttpServer::new(move || {
         App::new()
             .app_data(state_data.clone())
-            .service(web::resource("/cat/fact").route(web::get().to(animal_facts::get_cat_fact)))
-            .service(web::resource("/dog/fact").route(web::get().to(animal_facts::get_dog_fact)))
-            .service(web::resource("/flag").route(web::post().to(flagging::set_flag)))
-            .service(web::resource("/").to(index))
-            .service(web::resource("/metrics").to(prom_stats))
-            .service(web::resource("/admin/fact/list").route(web::post().to(admin::modify_fact)))
-            .service(web::resource("/admin/fact/add").route(web::post().to(admin::modify_fact)))
-            .service(web::resource("/admin/fact/delete").route(web::post().to(admin::modify_fact)))
-            .service(web::resource("/admin/flag/list").route(web::post().to(admin::modify_flag)))
-            .service(web::resource("/admin/flag/add").route(web::post().to(admin::modify_flag)))
-            .service(web::resource("/admin/flag/delete").route(web::post().to(admin::modify_flag)))
+            .configure(routes::configure)
     })
     .bind(server_binding)
     .expect("Failed to bind to a port or IP!")
     .run()
     .await
     .unwrap();
+
+    if let Err(err) = shutdown_data.shuffle_bags.save() {
+        error!("Failed saving the shuffle bags: {}", err);
+    }
 }
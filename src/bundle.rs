@@ -0,0 +1,415 @@
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::animal_facts::{AnimalLists, Fact, TrashedFact};
+use crate::reload::duplicate_ids;
+use crate::storage::{FactChange, FlagChange, Storage, StorageError};
+use crate::{APIState, Animal, AnimalType, Config, FactFlag};
+
+// Raised whenever the layout of a bundle changes, so older bundles can still be recognized
+pub const BUNDLE_VERSION: u32 = 1;
+
+// The largest request body `/admin/restore` accepts
+pub const MAX_BUNDLE_SIZE: usize = 64 * 1024 * 1024;
+
+// Everything an instance holds, for backing it up or moving it somewhere else
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Bundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub counts: BundleCounts,
+    pub animals: BTreeMap<Animal, AnimalBundle>,
+    // Left out if no flags were stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flags: Option<Vec<FactFlag>>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct BundleCounts {
+    pub facts: usize,
+    pub trash: usize,
+    pub flags: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct AnimalBundle {
+    pub facts: Vec<Fact>,
+    #[serde(default)]
+    pub trash: Vec<TrashedFact>,
+}
+
+impl Bundle {
+    pub fn new(animals: BTreeMap<Animal, AnimalBundle>, flags: Option<Vec<FactFlag>>) -> Self {
+        let counts = BundleCounts {
+            facts: animals.values().map(|lists| lists.facts.len()).sum(),
+            trash: animals.values().map(|lists| lists.trash.len()).sum(),
+            flags: flags.as_ref().map_or(0, Vec::len),
+        };
+
+        Bundle {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            counts,
+            animals,
+            flags,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    // Each animal in the bundle, and the flags if it has any, are swapped for what the bundle holds.
+    // Animals that aren't in the bundle are left alone.
+    Replace,
+    // Facts and flags with IDs that aren't present yet are added, and everything else is kept as it is
+    Merge,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    // How much was taken from the bundle
+    pub facts: usize,
+    pub trash: usize,
+    pub flags: usize,
+    // Entries of the bundle that were already present, which only happens when merging
+    pub skipped: usize,
+}
+
+#[derive(Debug)]
+pub enum RestoreError {
+    // The bundle can't be restored, and nothing was changed
+    Invalid(String),
+    // Saving failed, and anything already saved was put back
+    Storage(StorageError),
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreError::Invalid(message) => f.write_str(message),
+            RestoreError::Storage(err) => write!(f, "the restored data couldn't be saved: {}", err),
+        }
+    }
+}
+
+impl From<StorageError> for RestoreError {
+    fn from(err: StorageError) -> Self {
+        RestoreError::Storage(err)
+    }
+}
+
+// Bundles up every loaded animal, along with the flags
+pub fn export_state(state: &APIState) -> Result<Bundle, StorageError> {
+    let mut animals = BTreeMap::new();
+    for animal in &state.config.animal_fact_types {
+        if let (Some(facts), Some(trash)) = (
+            state.fact_lists.get(&animal.slug),
+            state.fact_lists.get_trash(&animal.slug),
+        ) {
+            // Both are held together, so a fact being deleted can't show up in neither or both
+            let facts = facts.read().unwrap();
+            let trash = trash.read().unwrap();
+            animals.insert(
+                animal.slug.clone(),
+                AnimalBundle {
+                    facts: facts.clone(),
+                    trash: trash.clone(),
+                },
+            );
+        }
+    }
+
+    let flags = match &state.fact_flags {
+        Some(flags) => Some(flags.read().unwrap().clone()),
+        // Flags are still kept in storage while flagging is turned off
+        None => state.storage.load_flags()?,
+    };
+
+    Ok(Bundle::new(animals, flags))
+}
+
+// Bundles up everything in storage, for when the server isn't running
+pub fn export_storage(storage: &dyn Storage, config: &Config) -> Result<Bundle, StorageError> {
+    let mut animals = BTreeMap::new();
+    for animal in &config.animal_fact_types {
+        if let Some(facts) = storage.load_facts(animal)? {
+            let trash = storage.load_trash(animal)?;
+            animals.insert(animal.slug.clone(), AnimalBundle { facts, trash });
+        }
+    }
+
+    Ok(Bundle::new(animals, storage.load_flags()?))
+}
+
+// Checks that every animal in the bundle is configured, and that no IDs are repeated
+fn validate(bundle: &Bundle, config: &Config) -> Result<(), RestoreError> {
+    if bundle.version > BUNDLE_VERSION {
+        return Err(RestoreError::Invalid(format!(
+            "The bundle is version {}, but only up to version {} is supported",
+            bundle.version, BUNDLE_VERSION
+        )));
+    }
+
+    let mut problems = Vec::new();
+    for (animal, lists) in &bundle.animals {
+        let animal_type = match config.animal_type(animal) {
+            Some(animal_type) => animal_type,
+            None => {
+                problems.push(format!("`{}` isn't a configured animal", animal));
+                continue;
+            }
+        };
+        problems.extend(duplicate_ids(
+            &format!("{} fact", animal_type.name),
+            lists
+                .facts
+                .iter()
+                .chain(lists.trash.iter().map(|trashed| &trashed.fact))
+                .map(|fact| fact.id),
+        ));
+    }
+    if let Some(flags) = &bundle.flags {
+        problems.extend(duplicate_ids("flag", flags.iter().map(|flag| flag.id)));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(RestoreError::Invalid(format!(
+            "The bundle can't be restored: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+// Works out an animal's lists once the bundle's are restored into them
+fn restore_animal(
+    facts: &[Fact],
+    trash: &[TrashedFact],
+    bundled: &AnimalBundle,
+    mode: RestoreMode,
+    report: &mut RestoreReport,
+) -> AnimalLists {
+    match mode {
+        RestoreMode::Replace => {
+            report.facts += bundled.facts.len();
+            report.trash += bundled.trash.len();
+            (bundled.facts.clone(), bundled.trash.clone())
+        }
+        RestoreMode::Merge => {
+            // A fact can't be in both the list and the trash, so they share IDs
+            let mut ids: HashSet<u64> = facts
+                .iter()
+                .chain(trash.iter().map(|trashed| &trashed.fact))
+                .map(|fact| fact.id)
+                .collect();
+            let mut facts = facts.to_vec();
+            let mut trash = trash.to_vec();
+
+            for fact in &bundled.facts {
+                if ids.insert(fact.id) {
+                    facts.push(fact.clone());
+                    report.facts += 1;
+                } else {
+                    report.skipped += 1;
+                }
+            }
+            for trashed in &bundled.trash {
+                if ids.insert(trashed.fact.id) {
+                    trash.push(trashed.clone());
+                    report.trash += 1;
+                } else {
+                    report.skipped += 1;
+                }
+            }
+
+            (facts, trash)
+        }
+    }
+}
+
+fn restore_flags(
+    flags: &[FactFlag],
+    bundled: &[FactFlag],
+    mode: RestoreMode,
+    report: &mut RestoreReport,
+) -> Vec<FactFlag> {
+    match mode {
+        RestoreMode::Replace => {
+            report.flags += bundled.len();
+            bundled.to_vec()
+        }
+        RestoreMode::Merge => {
+            let mut ids: HashSet<u64> = flags.iter().map(|flag| flag.id).collect();
+            let mut flags = flags.to_vec();
+            for flag in bundled {
+                if ids.insert(flag.id) {
+                    flags.push(flag.clone());
+                    report.flags += 1;
+                } else {
+                    report.skipped += 1;
+                }
+            }
+            flags
+        }
+    }
+}
+
+// Saves every restored animal and the flags. If any save fails, the ones that already
+// succeeded are saved again with what they held before, so storage is left as it was.
+fn save_restored(
+    storage: &dyn Storage,
+    animals: &[(&AnimalType, AnimalLists, AnimalLists)],
+    flags: Option<&(Vec<FactFlag>, Vec<FactFlag>)>,
+) -> Result<(), StorageError> {
+    let save_animal = |animal: &AnimalType, (facts, trash): &AnimalLists| {
+        let change = FactChange::Replaced {
+            facts: facts.clone(),
+            trash: trash.clone(),
+        };
+        storage.save_facts(animal, facts, trash, &change)
+    };
+
+    let mut saved = 0;
+    let mut result = Ok(());
+    for (animal, _, new) in animals {
+        result = save_animal(animal, new);
+        if result.is_err() {
+            break;
+        }
+        saved += 1;
+    }
+    if let (Ok(()), Some((_, new))) = (&result, flags) {
+        result = storage.save_flags(new, &FlagChange::Replaced(new.clone()));
+    }
+
+    if result.is_err() {
+        for (animal, old, _) in &animals[..saved] {
+            if let Err(err) = save_animal(animal, old) {
+                error!("Failed putting back the {} facts: {}", animal.name, err);
+            }
+        }
+    }
+    result
+}
+
+// Restores a bundle into the running API. Every affected list is locked for the whole restore,
+// so nothing changed through the API in the meantime can be lost.
+pub fn restore_state(
+    state: &APIState,
+    bundle: &Bundle,
+    mode: RestoreMode,
+) -> Result<RestoreReport, RestoreError> {
+    validate(bundle, &state.config)?;
+
+    // The bundle's animals are already sorted, which keeps the lists locked in the usual order
+    let mut locked = Vec::with_capacity(bundle.animals.len());
+    for animal in bundle.animals.keys() {
+        // Validation made sure the animal is configured, and every configured animal has lists
+        let animal_type = state.config.animal_type(animal).unwrap();
+        let (facts, trash) = match state.fact_lists.get_unloaded(animal) {
+            Some(lists) => lists,
+            None => {
+                return Err(RestoreError::Invalid(format!(
+                    "The {} facts aren't available",
+                    animal_type.name
+                )))
+            }
+        };
+        locked.push((animal_type, facts.write().unwrap(), trash));
+    }
+    let mut locked: Vec<_> = locked
+        .into_iter()
+        .map(|(animal_type, facts, trash)| (animal_type, facts, trash.write().unwrap()))
+        .collect();
+    let mut flag_lock = state
+        .fact_flags
+        .as_ref()
+        .map(|flags| flags.write().unwrap());
+
+    let mut report = RestoreReport::default();
+    let mut restored = Vec::with_capacity(locked.len());
+    for (animal_type, facts, trash) in &locked {
+        let new = restore_animal(
+            facts,
+            trash,
+            &bundle.animals[&animal_type.slug],
+            mode,
+            &mut report,
+        );
+        restored.push((*animal_type, ((**facts).clone(), (**trash).clone()), new));
+    }
+
+    let flags = match &bundle.flags {
+        Some(bundled) => {
+            let stored = match &flag_lock {
+                Some(flags) => (**flags).clone(),
+                // Flags are still kept in storage while flagging is turned off
+                None => state.storage.load_flags()?.unwrap_or_default(),
+            };
+            let new = restore_flags(&stored, bundled, mode, &mut report);
+            Some((stored, new))
+        }
+        None => None,
+    };
+
+    save_restored(state.storage.as_ref(), &restored, flags.as_ref())?;
+
+    let mut index = state.search_index.write().unwrap();
+    for ((animal_type, facts, trash), (_, old, new)) in locked.iter_mut().zip(restored) {
+        for fact in &old.0 {
+            index.remove(&animal_type.slug, fact.id);
+        }
+        index.insert_all(&animal_type.slug, &new.0);
+
+        state
+            .fact_lists
+            .set_loaded(animal_type.slug.clone(), !new.0.is_empty());
+        **facts = new.0;
+        **trash = new.1;
+    }
+    if let (Some(flag_lock), Some((_, new))) = (flag_lock.as_mut(), flags) {
+        **flag_lock = new;
+    }
+
+    Ok(report)
+}
+
+// Restores a bundle straight into storage, for when the server isn't running
+pub fn restore_storage(
+    storage: &dyn Storage,
+    config: &Config,
+    bundle: &Bundle,
+    mode: RestoreMode,
+) -> Result<RestoreReport, RestoreError> {
+    validate(bundle, config)?;
+
+    let mut report = RestoreReport::default();
+    let mut restored = Vec::with_capacity(bundle.animals.len());
+    for (animal, bundled) in &bundle.animals {
+        let animal_type = config.animal_type(animal).unwrap();
+        let facts = storage.load_facts(animal_type)?.unwrap_or_default();
+        let trash = storage.load_trash(animal_type)?;
+
+        let new = restore_animal(&facts, &trash, bundled, mode, &mut report);
+        restored.push((animal_type, (facts, trash), new));
+    }
+
+    let flags = match &bundle.flags {
+        Some(bundled) => {
+            let stored = storage.load_flags()?.unwrap_or_default();
+            let new = restore_flags(&stored, bundled, mode, &mut report);
+            Some((stored, new))
+        }
+        None => None,
+    };
+
+    save_restored(storage, &restored, flags.as_ref())?;
+
+    Ok(report)
+}
@@ -0,0 +1,180 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::web::{Data, HttpResponse, Path, Query};
+use actix_web::HttpRequest;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::animal_facts::{find_list, Fact, PublicFact};
+use crate::language::requested_languages;
+use crate::{generate_response, APIState, Animal, RESP_BAD_TIMEZONE, RESP_NO_MATCHING_FACTS};
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct DailyQuery {
+    // The timezone whose midnight starts a new day, ex. `Europe/Berlin`. Defaults to `daily_timezone`
+    pub tz: Option<String>,
+    pub lang: Option<String>,
+}
+
+// The fact of the day, which is a normal fact along with the day it's for
+#[derive(Serialize, Debug)]
+pub struct DailyFact<'a> {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub fact: PublicFact<'a>,
+}
+
+// When the day starts in the timezone. Some timezones skip midnight for daylight saving time, in which case
+// the day starts at the first time that does exist.
+pub fn day_start(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    (0..24)
+        .find_map(|hour| {
+            tz.from_local_datetime(&(midnight + Duration::hours(hour)))
+                .earliest()
+        })
+        .expect("Every day has an hour that exists")
+        .with_timezone(&Utc)
+}
+
+// FNV-1a, which unlike the standard library's hasher is guaranteed to give the same result in every version
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Picks the same fact for everyone on a given day. Every fact gets a score from hashing it with the day, and
+// the highest one wins, so adding, removing or flagging other facts never changes the pick. Only facts
+// that existed when the day started are counted, and facts from before `created_at` was recorded always
+// count. Weights and flags change during the day, so the only fact left out for them is one with a weight
+// of 0, which is never picked at random either.
+pub fn daily_pick<'a>(
+    facts: &[&'a Fact],
+    animal: &Animal,
+    date: NaiveDate,
+    start: DateTime<Utc>,
+) -> Option<&'a Fact> {
+    facts
+        .iter()
+        .filter(|fact| {
+            let existed = match fact.created_at {
+                Some(created) => created <= start,
+                None => true,
+            };
+            fact.weight() > 0.0 && existed
+        })
+        .max_by_key(|fact| stable_hash(&format!("{}/{}/{}", animal, date, fact.id)))
+        .copied()
+}
+
+pub fn get_daily_fact(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    animal: Path<String>,
+    query: Query<DailyQuery>,
+) -> HttpResponse {
+    let (animal_type, fact_list) = match find_list(&app_data, &animal) {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    let tz: Tz = match &query.tz {
+        Some(tz) => match tz.parse() {
+            Ok(tz) => tz,
+            Err(_) => return generate_response(&RESP_BAD_TIMEZONE),
+        },
+        None => app_data.config.daily_timezone,
+    };
+
+    let now = Utc::now();
+    let today = now.with_timezone(&tz).date_naive();
+    let start = day_start(tz, today);
+    let rollover = day_start(tz, today.succ_opt().unwrap());
+
+    let list_lock = fact_list.read().unwrap();
+    let facts: Vec<&Fact> = list_lock.iter().collect();
+    let fact = match daily_pick(&facts, &animal_type.slug, today, start) {
+        Some(fact) => fact,
+        None => return generate_response(&RESP_NO_MATCHING_FACTS),
+    };
+
+    app_data
+        .req_counter
+        .with_label_values(&[&animal_type.name])
+        .inc();
+
+    let languages = requested_languages(&req, query.lang.as_deref());
+    let mut resp = app_data.json_response(&DailyFact {
+        date: today,
+        fact: PublicFact::localized(fact, &languages, &app_data.config.default_language),
+    });
+
+    // Caches can keep the fact until the next one is picked
+    if resp.status().is_success() {
+        let max_age = (rollover - now).num_seconds().max(0);
+        let headers = resp.headers_mut();
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_str(&format!("public, max-age={}", max_age)).unwrap(),
+        );
+        headers.insert(
+            header::EXPIRES,
+            HeaderValue::from_str(&rollover.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+                .unwrap(),
+        );
+    }
+
+    resp
+}
+
+#[cfg(test)]
+mod daily_tests {
+    use super::*;
+
+    #[test]
+    fn unaffected_by_other_facts() {
+        let mut facts: Vec<Fact> = (1..=5)
+            .map(|id| Fact::new(id, &format!("Fact {}", id)))
+            .collect();
+        let cat = Animal::from("cat");
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let start = day_start(chrono_tz::UTC, date);
+
+        let all: Vec<&Fact> = facts.iter().collect();
+        let pick = daily_pick(&all, &cat, date, start).unwrap().id;
+
+        // Taking out another fact leaves the pick alone
+        let removed = if pick == 1 { 2 } else { 1 };
+        let others: Vec<&Fact> = facts.iter().filter(|fact| fact.id != removed).collect();
+        assert_eq!(daily_pick(&others, &cat, date, start).unwrap().id, pick);
+
+        // A fact that can never be picked at random isn't the fact of the day either
+        for fact in facts.iter_mut().filter(|fact| fact.id == pick) {
+            fact.weight = Some(0.0);
+        }
+        let all: Vec<&Fact> = facts.iter().collect();
+        assert_ne!(daily_pick(&all, &cat, date, start).unwrap().id, pick);
+
+        for fact in facts.iter_mut() {
+            fact.weight = Some(0.0);
+        }
+        let all: Vec<&Fact> = facts.iter().collect();
+        assert!(daily_pick(&all, &cat, date, start).is_none());
+    }
+
+    #[test]
+    fn skipped_midnight() {
+        // Santiago moved its clocks from midnight to 1 AM on this day
+        let date = NaiveDate::from_ymd_opt(2022, 9, 11).unwrap();
+        let start = day_start(chrono_tz::America::Santiago, date);
+        assert_eq!(start.to_rfc3339(), "2022-09-11T04:00:00+00:00");
+    }
+
+    #[test]
+    fn day_start_in_timezone() {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let start = day_start(chrono_tz::Europe::Berlin, date);
+        assert_eq!(start.to_rfc3339(), "2024-06-30T22:00:00+00:00");
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::animal_facts::Fact;
+use crate::search::tokenize;
+
+// How new facts that resemble existing ones of the same animal are handled
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct DuplicateConfig {
+    // How much of their wording, from 0 to 1, two facts have to share to be similar. Facts that only
+    // differ in case or spacing are always similar, so anything above 1 only catches those.
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    #[serde(default)]
+    pub action: DuplicateAction,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        DuplicateConfig {
+            threshold: default_threshold(),
+            action: DuplicateAction::default(),
+        }
+    }
+}
+
+fn default_threshold() -> f64 {
+    0.8
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    // The fact is added, and the response lists the facts it's similar to
+    #[default]
+    Warn,
+    // The fact isn't added
+    Reject,
+}
+
+// The response to adding a fact that's similar to others, whether or not it was added
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SimilarResp {
+    pub code: u16,
+    pub message: Cow<'static, str>,
+    pub similar_ids: Vec<u64>,
+}
+
+// Facts are considered the same regardless of case or spacing
+pub fn normalize_content(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct Entry {
+    id: u64,
+    word_count: usize,
+}
+
+// Finds facts similar to new content. Similarity is the share of words two facts have in common,
+// counted with the same rules as search, ex. `Cats purr` and `A cat purrs` share every word.
+pub struct DuplicateChecker {
+    threshold: f64,
+    entries: Vec<Entry>,
+    // Each normalized content, and the entries that have it
+    contents: HashMap<String, Vec<usize>>,
+    // Each word, and the entries containing it
+    words: HashMap<String, Vec<usize>>,
+}
+
+impl DuplicateChecker {
+    pub fn new<'a>(config: &DuplicateConfig, facts: impl IntoIterator<Item = &'a Fact>) -> Self {
+        let mut checker = DuplicateChecker {
+            threshold: config.threshold,
+            entries: Vec::new(),
+            contents: HashMap::new(),
+            words: HashMap::new(),
+        };
+        for fact in facts {
+            checker.insert(fact.id, &fact.content);
+        }
+        checker
+    }
+
+    pub fn insert(&mut self, id: u64, content: &str) {
+        let pos = self.entries.len();
+        let words = unique_words(content);
+
+        self.entries.push(Entry {
+            id,
+            word_count: words.len(),
+        });
+        self.contents
+            .entry(normalize_content(content))
+            .or_default()
+            .push(pos);
+        for word in words {
+            self.words.entry(word).or_default().push(pos);
+        }
+    }
+
+    // The IDs of every fact similar to the content, most similar first
+    pub fn similar(&self, content: &str) -> Vec<u64> {
+        self.similar_entries(content)
+            .into_iter()
+            .map(|pos| self.entries[pos].id)
+            .collect()
+    }
+
+    fn similar_entries(&self, content: &str) -> Vec<usize> {
+        let words = unique_words(content);
+
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for word in &words {
+            for pos in self.words.get(word).into_iter().flatten() {
+                *shared.entry(*pos).or_insert(0) += 1;
+            }
+        }
+
+        let mut scores: HashMap<usize, f64> = shared
+            .into_iter()
+            .map(|(pos, shared)| {
+                let total = words.len() + self.entries[pos].word_count - shared;
+                (pos, shared as f64 / total as f64)
+            })
+            .filter(|(_, score)| *score >= self.threshold)
+            .collect();
+        for pos in self
+            .contents
+            .get(&normalize_content(content))
+            .into_iter()
+            .flatten()
+        {
+            scores.insert(*pos, 1.0);
+        }
+
+        let mut similar: Vec<(usize, f64)> = scores.into_iter().collect();
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        similar.into_iter().map(|(pos, _)| pos).collect()
+    }
+}
+
+fn unique_words(content: &str) -> Vec<String> {
+    let mut words: Vec<String> = tokenize(content).collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+// Groups the facts that are similar to each other, directly or through another fact. Only groups of
+// more than one fact are returned, in the order their first fact appears in the list.
+pub fn clusters<'a>(facts: &'a [Fact], config: &DuplicateConfig) -> Vec<Vec<&'a Fact>> {
+    let mut checker = DuplicateChecker::new(config, []);
+    let mut parents: Vec<usize> = (0..facts.len()).collect();
+
+    for (pos, fact) in facts.iter().enumerate() {
+        for other in checker.similar_entries(&fact.content) {
+            let (root, other_root) = (find_root(&mut parents, pos), find_root(&mut parents, other));
+            parents[root.max(other_root)] = root.min(other_root);
+        }
+        checker.insert(fact.id, &fact.content);
+    }
+
+    let mut groups: Vec<Vec<&Fact>> = vec![Vec::new(); facts.len()];
+    for (pos, fact) in facts.iter().enumerate() {
+        let root = find_root(&mut parents, pos);
+        groups[root].push(fact);
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+fn find_root(parents: &mut [usize], mut pos: usize) -> usize {
+    while parents[pos] != pos {
+        parents[pos] = parents[parents[pos]];
+        pos = parents[pos];
+    }
+    pos
+}
+
+#[cfg(test)]
+mod duplicate_tests {
+    use super::*;
+
+    #[test]
+    fn similar_wording() {
+        let facts = [
+            Fact::new(1, "Cats sleep for 16 hours a day."),
+            Fact::new(2, "A cat sleeps 16 hours a day."),
+            Fact::new(3, "Dogs have three eyelids."),
+        ];
+        let checker = DuplicateChecker::new(&DuplicateConfig::default(), &facts);
+
+        assert_eq!(checker.similar("cats sleep for 16 hours a day"), [1, 2]);
+        assert_eq!(checker.similar("DOGS   have three eyelids."), [3]);
+        assert!(checker.similar("Dogs dream.").is_empty());
+    }
+
+    #[test]
+    fn exact_matches_only() {
+        let config = DuplicateConfig {
+            threshold: 1.1,
+            action: DuplicateAction::Reject,
+        };
+        let checker = DuplicateChecker::new(&config, &[Fact::new(1, "Cats purr.")]);
+
+        assert_eq!(checker.similar(" cats PURR. "), [1]);
+        assert!(checker.similar("Cats purr").is_empty());
+    }
+
+    #[test]
+    fn clusters_are_joined() {
+        let facts = [
+            Fact::new(1, "Cats sleep for 16 hours a day."),
+            Fact::new(2, "Dogs have three eyelids."),
+            Fact::new(3, "Cats sleep 16 hours a day."),
+            Fact::new(4, "Dogs have 3 eyelids."),
+            Fact::new(5, "Dogs have three eyelids!"),
+            Fact::new(6, "Cats purr."),
+        ];
+        let clusters: Vec<Vec<u64>> = clusters(&facts, &DuplicateConfig::default())
+            .into_iter()
+            .map(|cluster| cluster.iter().map(|fact| fact.id).collect())
+            .collect();
+
+        assert_eq!(clusters, [vec![1, 3], vec![2, 5]]);
+    }
+}
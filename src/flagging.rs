@@ -1,10 +1,9 @@
 use actix_web::web::{Data, Json};
 use actix_web::HttpResponse;
-use rand::RngCore;
 use subtle::ConstantTimeEq;
 
-use std::{fs, path::Path};
-
+use crate::admin::modify_persistent_flag;
+use crate::storage::FlagChange;
 use crate::*;
 
 fn check_flagger(unchecked_auth: String, flagger_list: &[Flagger]) -> Option<Flagger> {
@@ -48,32 +47,32 @@ pub fn set_flag(app_data: Data<APIState>, body: Json<FactFlagRequest>) -> HttpRe
             reason,
             ..
         } = body;
-        let id = rand::thread_rng().next_u64();
-
-        let mut flag_list = flag_list.write().unwrap();
-
         // Check to make sure the targeted fact exists
-        if !flag_list
-            .iter()
-            .enumerate()
-            .any(|(_, flag)| flag.fact_id == fact_id)
-        {
+        if !app_data.fact_exists(&fact_type, fact_id) {
             return generate_response(&RESP_ID_NOT_FOUND);
         }
 
-        flag_list.push(FactFlag {
+        let mut flag_list = flag_list.write().unwrap();
+        let id = app_data
+            .config
+            .id_scheme
+            .next_id(&ids::flag_ids(&flag_list));
+
+        let flag = FactFlag {
             id,
             fact_type,
             fact_id,
             reason,
             flagger,
-        })
-    }
+        };
+        flag_list.push(flag.clone());
 
-    let file_path = Path::new(&app_data.config.facts_dir).join("fact_flags.json");
-    fs::write(file_path, serde_json::to_string_pretty(flag_list).unwrap())
-        .expect("Failed writing to flags file!");
+        if let Err(resp) = modify_persistent_flag(&flag_list, FlagChange::Added(flag), &app_data) {
+            flag_list.pop();
+            return resp;
+        }
+    }
 
-    let resp = JsonResp::new(201, CreatedAction::Flag.as_str());
+    let resp = JsonResp::with_message(201, CreatedAction::Flag.message());
     generate_response(&resp)
 }
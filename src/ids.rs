@@ -0,0 +1,179 @@
+use chrono::Utc;
+use rand::{Rng, RngCore};
+use serde::de::Deserializer;
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::HashSet;
+
+use crate::animal_facts::{Fact, TrashedFact};
+use crate::FactFlag;
+
+// The largest integer a JavaScript number can hold exactly, 2^53 - 1
+pub const MAX_SAFE_ID: u64 = (1 << 53) - 1;
+
+// Snowflake IDs count milliseconds from the start of 2020 above a 12 bit random sequence.
+// That keeps them under `MAX_SAFE_ID` until 2089.
+const SNOWFLAKE_EPOCH_MS: i64 = 1_577_836_800_000;
+const SNOWFLAKE_SEQUENCE_BITS: u32 = 12;
+
+// How new fact and flag IDs are picked. ULIDs aren't offered since they're 128 bits, and IDs are
+// stored as 64 bit numbers everywhere.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdScheme {
+    // Any 64 bit number, which is what every ID was before the scheme could be picked
+    #[default]
+    Random,
+    // One more than the highest ID in use, ex. 1, 2, 3
+    Monotonic,
+    // The time the ID was made followed by a random sequence, so IDs roughly sort by age
+    Snowflake,
+}
+
+impl IdScheme {
+    // Picks an ID that isn't in `taken`. Every scheme but `Random` stays under `MAX_SAFE_ID`.
+    pub fn next_id(self, taken: &HashSet<u64>) -> u64 {
+        let mut rng = rand::thread_rng();
+
+        match self {
+            IdScheme::Random => loop {
+                let id = rng.next_u64();
+                if !taken.contains(&id) {
+                    break id;
+                }
+            },
+            IdScheme::Monotonic => {
+                // Random IDs from before the scheme changed are ignored, or there'd be no room left
+                let mut id = taken
+                    .iter()
+                    .filter(|id| **id < MAX_SAFE_ID)
+                    .max()
+                    .map_or(1, |id| id + 1);
+                while taken.contains(&id) {
+                    id += 1;
+                }
+                id
+            }
+            IdScheme::Snowflake => loop {
+                let millis = (Utc::now().timestamp_millis() - SNOWFLAKE_EPOCH_MS).max(0) as u64;
+                let sequence = rng.gen_range(0..1 << SNOWFLAKE_SEQUENCE_BITS);
+                let id = (millis << SNOWFLAKE_SEQUENCE_BITS) | sequence;
+                if !taken.contains(&id) {
+                    break id;
+                }
+            },
+        }
+    }
+}
+
+// Every ID a new fact can't reuse, which includes the ones in the trash so restoring never clashes
+pub fn fact_ids(facts: &[Fact], trash: &[TrashedFact]) -> HashSet<u64> {
+    facts
+        .iter()
+        .chain(trash.iter().map(|trashed| &trashed.fact))
+        .map(|fact| fact.id)
+        .collect()
+}
+
+pub fn flag_ids(flags: &[FactFlag]) -> HashSet<u64> {
+    flags.iter().map(|flag| flag.id).collect()
+}
+
+// Turns every ID in a response into a string, for clients that can't hold a 64 bit number exactly.
+// Any field named `id`, or ending in `_id` or `_ids`, is taken to be one.
+pub fn stringify_ids(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if name == "id" || name.ends_with("_id") || name.ends_with("_ids") {
+                    stringify(field);
+                } else {
+                    stringify_ids(field);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(stringify_ids),
+        _ => {}
+    }
+}
+
+fn stringify(value: &mut Value) {
+    match value {
+        Value::Number(number) => *value = Value::String(number.to_string()),
+        Value::Array(values) => values.iter_mut().for_each(stringify),
+        _ => {}
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawId {
+    Number(u64),
+    Text(String),
+}
+
+impl RawId {
+    fn parse<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            RawId::Number(id) => Ok(id),
+            RawId::Text(id) => id
+                .parse()
+                .map_err(|_| E::custom(format!("`{}` isn't a valid ID", id))),
+        }
+    }
+}
+
+// IDs are accepted as either numbers or strings, so anything sent back from a response works
+pub fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    RawId::deserialize(deserializer)?.parse()
+}
+
+pub fn deserialize_optional_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<RawId>::deserialize(deserializer)?
+        .map(RawId::parse)
+        .transpose()
+}
+
+#[cfg(test)]
+mod id_tests {
+    use super::*;
+
+    #[test]
+    fn monotonic_skips_random_ids() {
+        let taken = HashSet::from([3, 7, u64::MAX - 5]);
+        assert_eq!(IdScheme::Monotonic.next_id(&taken), 8);
+        assert_eq!(IdScheme::Monotonic.next_id(&HashSet::new()), 1);
+    }
+
+    #[test]
+    fn snowflakes_are_safe() {
+        let id = IdScheme::Snowflake.next_id(&HashSet::new());
+        assert!(id <= MAX_SAFE_ID);
+        assert!(id > 1 << 40);
+    }
+
+    #[test]
+    fn nested_ids_are_stringified() {
+        let mut value = serde_json::json!({
+            "id": 12603598811629753927u64,
+            "fact_id": 5,
+            "similar_ids": [1, 2],
+            "count": 3,
+            "facts": [{"id": 1, "content": "Dogs dream."}],
+        });
+        stringify_ids(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "id": "12603598811629753927",
+                "fact_id": "5",
+                "similar_ids": ["1", "2"],
+                "count": 3,
+                "facts": [{"id": "1", "content": "Dogs dream."}],
+            })
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::animal_facts::{Fact, Revision, TrashedFact};
+use crate::duplicates::{normalize_content, DuplicateAction, DuplicateChecker};
+use crate::ids;
+use crate::storage::{FactChange, Storage};
+use crate::{AnimalType, Config};
+
+// The field holding each fact's content when the import doesn't name one
+pub const DEFAULT_CONTENT_FIELD: &str = "content";
+
+// The largest request body `/admin/fact/import` accepts, which is well above any other admin request
+pub const MAX_IMPORT_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    // An array of strings, or of objects holding the content in the content field
+    Json,
+    // A header row naming the columns, with the content in the content field's column
+    Csv,
+    // Each non-empty line is a fact
+    Text,
+}
+
+impl ImportFormat {
+    // Guesses the format from a file's extension, which is JSON unless it says otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ImportFormat::Csv,
+            Some(ext) if ext.eq_ignore_ascii_case("txt") => ImportFormat::Text,
+            _ => ImportFormat::Json,
+        }
+    }
+}
+
+// A fact read from an import, before it has been given an ID
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportEntry {
+    pub content: String,
+    pub source: Option<String>,
+}
+
+// An entry, or why it couldn't be used as a fact
+type ParsedEntry = Result<ImportEntry, String>;
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub added: usize,
+    // Entries matching a fact that already exists, or an earlier entry of the same import
+    pub skipped: usize,
+    // Entries that couldn't be read as a fact
+    pub rejected: usize,
+    // Why each entry was rejected, with entries counted from 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<String>,
+    // Entries that resemble facts which already exist, whether or not they were added
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub similar: Vec<SimilarEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SimilarEntry {
+    // Counted from 1, like the rejections
+    pub entry: usize,
+    pub similar_ids: Vec<u64>,
+}
+
+// Reads every entry out of the data. Data that isn't in the format at all is an error,
+// but single entries that can't be used are returned as rejections for the report.
+pub fn parse(
+    data: &str,
+    format: ImportFormat,
+    content_field: &str,
+) -> Result<Vec<ParsedEntry>, String> {
+    match format {
+        ImportFormat::Json => parse_json(data, content_field),
+        ImportFormat::Csv => parse_csv(data, content_field),
+        ImportFormat::Text => Ok(data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                Ok(ImportEntry {
+                    content: line.trim().to_string(),
+                    source: None,
+                })
+            })
+            .collect()),
+    }
+}
+
+fn parse_json(data: &str, content_field: &str) -> Result<Vec<ParsedEntry>, String> {
+    let values = match serde_json::from_str(data) {
+        Ok(Value::Array(values)) => values,
+        Ok(_) => return Err("The JSON must be an array of facts".to_string()),
+        Err(err) => return Err(format!("The JSON couldn't be read: {}", err)),
+    };
+
+    Ok(values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            Value::String(content) => entry(i, content, None),
+            Value::Object(fields) => match fields.get(content_field) {
+                Some(Value::String(content)) => entry(
+                    i,
+                    content,
+                    fields
+                        .get("source")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                ),
+                _ => Err(format!("Entry {} has no `{}` text", i + 1, content_field)),
+            },
+            _ => Err(format!("Entry {} isn't text or an object", i + 1)),
+        })
+        .collect())
+}
+
+fn parse_csv(data: &str, content_field: &str) -> Result<Vec<ParsedEntry>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("The CSV header couldn't be read: {}", err))?
+        .clone();
+    let content_column = headers
+        .iter()
+        .position(|header| header.trim() == content_field)
+        .ok_or_else(|| format!("The CSV has no `{}` column", content_field))?;
+    let source_column = headers.iter().position(|header| header.trim() == "source");
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let record =
+                record.map_err(|err| format!("Entry {} couldn't be read: {}", i + 1, err))?;
+            let source = source_column
+                .and_then(|column| record.get(column))
+                .filter(|source| !source.trim().is_empty())
+                .map(|source| source.trim().to_string());
+
+            match record.get(content_column) {
+                Some(content) => entry(i, content, source),
+                None => Err(format!("Entry {} has no `{}` column", i + 1, content_field)),
+            }
+        })
+        .collect())
+}
+
+fn entry(i: usize, content: &str, source: Option<String>) -> ParsedEntry {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(format!("Entry {} has no content", i + 1));
+    }
+
+    Ok(ImportEntry {
+        content: content.to_string(),
+        source,
+    })
+}
+
+// Turns the entries into new facts, skipping any that match a fact in the list or trash. Entries similar
+// to a fact in the list, or to an earlier entry, are reported and handled as the config says.
+// Each new fact gets an ID from the config's scheme which isn't used by any other fact of the animal.
+pub fn new_facts(
+    facts: &[Fact],
+    trash: &[TrashedFact],
+    entries: Vec<ParsedEntry>,
+    created_by: Option<&str>,
+    config: &Config,
+) -> (Vec<Fact>, ImportReport) {
+    let mut ids = ids::fact_ids(facts, trash);
+    let mut contents: HashSet<String> = facts
+        .iter()
+        .chain(trash.iter().map(|trashed| &trashed.fact))
+        .map(|fact| normalize_content(&fact.content))
+        .collect();
+    let mut checker = DuplicateChecker::new(&config.duplicates, facts);
+
+    let now = Utc::now();
+    let mut added = Vec::new();
+    let mut report = ImportReport::default();
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(reason) => {
+                report.rejected += 1;
+                report.rejections.push(reason);
+                continue;
+            }
+        };
+        if !contents.insert(normalize_content(&entry.content)) {
+            report.skipped += 1;
+            continue;
+        }
+
+        let similar_ids = checker.similar(&entry.content);
+        if !similar_ids.is_empty() {
+            report.similar.push(SimilarEntry {
+                entry: i + 1,
+                similar_ids,
+            });
+            if config.duplicates.action == DuplicateAction::Reject {
+                report.rejected += 1;
+                report.rejections.push(format!(
+                    "Entry {} is too similar to an existing fact",
+                    i + 1
+                ));
+                continue;
+            }
+        }
+
+        let id = config.id_scheme.next_id(&ids);
+        ids.insert(id);
+        checker.insert(id, &entry.content);
+        added.push(Fact {
+            id,
+            content: entry.content.clone(),
+            created_at: Some(now),
+            created_by: created_by.map(str::to_string),
+            source: entry.source,
+            history: vec![Revision {
+                content: entry.content,
+                revised_at: Some(now),
+                revised_by: created_by.map(str::to_string),
+            }],
+            ..Default::default()
+        });
+    }
+
+    report.added = added.len();
+    (added, report)
+}
+
+// Imports straight into storage, without a running server. An animal with nothing stored yet is started
+// from scratch, so this can also be used to create an animal's facts.
+pub fn import_into_storage(
+    storage: &dyn Storage,
+    animal: &AnimalType,
+    entries: Vec<ParsedEntry>,
+    config: &Config,
+) -> Result<ImportReport, String> {
+    let load_error = |err| format!("The {} facts couldn't be loaded: {}", animal.name, err);
+    let mut facts = storage
+        .load_facts(animal)
+        .map_err(load_error)?
+        .unwrap_or_default();
+    let trash = storage.load_trash(animal).map_err(load_error)?;
+
+    let (added, report) = new_facts(&facts, &trash, entries, None, config);
+    if !added.is_empty() {
+        facts.extend(added.iter().cloned());
+        storage
+            .save_facts(animal, &facts, &trash, &FactChange::Imported(added))
+            .map_err(|err| format!("The {} facts couldn't be saved: {}", animal.name, err))?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,158 @@
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::HttpRequest;
+use serde::{Deserialize, Deserializer};
+
+use crate::animal_facts::Fact;
+
+pub fn normalize_language(language: &str) -> String {
+    language.trim().replace('_', "-").to_lowercase()
+}
+
+// Lets `default_language` be written any way a client might send it, ex. `en_US`
+pub fn deserialize_language<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    Ok(normalize_language(&String::deserialize(deserializer)?))
+}
+
+// Collects the languages a client asked for, most preferred first.
+// An explicit `?lang=` always wins over the `Accept-Language` header.
+pub fn requested_languages(req: &HttpRequest, query_lang: Option<&str>) -> Vec<String> {
+    let mut languages = Vec::new();
+
+    if let Some(lang) = query_lang {
+        languages.push(normalize_language(lang));
+    }
+
+    if let Some(header) = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+    {
+        languages.extend(parse_accept_language(header));
+    }
+
+    languages
+}
+
+// Parses a header such as `da, en-gb;q=0.8, en;q=0.7`, skipping wildcards and anything
+// the client marked as unacceptable with `q=0`.
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let language = normalize_language(parts.next()?);
+
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if language.is_empty() || language == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((language, quality))
+            }
+        })
+        .collect();
+
+    // A stable sort keeps the header's order for equal weights
+    weighted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    weighted.into_iter().map(|(language, _)| language).collect()
+}
+
+// Picks the best translation of a fact for the requested languages, returning the language
+// used along with the content. Regional requests like `pt-br` fall back to `pt` before moving on,
+// and the fact's base content in the default language is used when nothing else matches.
+// A regional default like `en-us` is also used for requests for `en`.
+pub fn localize<'a>(
+    fact: &'a Fact,
+    requested: &[String],
+    default_language: &'a str,
+) -> (&'a str, &'a str) {
+    let default_primary = default_language
+        .split('-')
+        .next()
+        .unwrap_or(default_language);
+
+    for language in requested {
+        let primary = language.split('-').next().unwrap_or(language);
+
+        for candidate in [language.as_str(), primary] {
+            if candidate == default_language || candidate == default_primary {
+                return (default_language, &fact.content);
+            }
+            if let Some((language, content)) = fact.translations.get_key_value(candidate) {
+                return (language, content);
+            }
+        }
+    }
+
+    (default_language, &fact.content)
+}
+
+#[cfg(test)]
+mod language_tests {
+    use super::{localize, parse_accept_language};
+    use crate::animal_facts::Fact;
+
+    fn gen_fact() -> Fact {
+        Fact {
+            translations: vec![
+                (
+                    "de".to_string(),
+                    "Katzen schmecken nichts Süßes.".to_string(),
+                ),
+                ("pt".to_string(), "Gatos não sentem sabor doce.".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            ..Fact::new(1, "Cats cannot taste anything sweet.")
+        }
+    }
+
+    #[test]
+    fn header_ordering() {
+        assert_eq!(
+            parse_accept_language("en;q=0.5, DE, fr;q=0, *;q=0.1, pt-BR;q=0.8"),
+            vec!["de", "pt-br", "en"]
+        );
+    }
+
+    #[test]
+    fn regional_fallback() {
+        let fact = gen_fact();
+        let requested = vec!["pt-br".to_string()];
+        assert_eq!(
+            localize(&fact, &requested, "en"),
+            ("pt", "Gatos não sentem sabor doce.")
+        );
+    }
+
+    #[test]
+    fn default_fallback() {
+        let fact = gen_fact();
+        let requested = vec!["ja".to_string()];
+        assert_eq!(
+            localize(&fact, &requested, "en"),
+            ("en", "Cats cannot taste anything sweet.")
+        );
+    }
+
+    #[test]
+    fn default_preferred_over_later_translation() {
+        let fact = gen_fact();
+        let requested = vec!["en-gb".to_string(), "de".to_string()];
+        assert_eq!(localize(&fact, &requested, "en").0, "en");
+    }
+
+    #[test]
+    fn regional_default() {
+        let fact = gen_fact();
+        for language in ["en", "en-us", "en-gb"] {
+            let requested = vec![language.to_string(), "de".to_string()];
+            assert_eq!(localize(&fact, &requested, "en-us").0, "en-us");
+        }
+    }
+}
@@ -3,25 +3,49 @@
 
 use actix_web::http::StatusCode;
 use actix_web::web::HttpResponse;
-use log::{info, warn};
-use prometheus::{IntCounter, IntCounterVec, Registry};
+use log::{error, info, warn};
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Registry};
 use serde::{Deserialize, Serialize};
 
+use std::borrow::Cow;
 use std::fmt;
-use std::fs;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 
 pub mod admin;
 pub mod animal_facts;
+pub mod bundle;
+pub mod daily;
+pub mod duplicates;
 pub mod flagging;
+pub mod ids;
+pub mod import;
+pub mod language;
+pub mod reload;
+pub mod routes;
+pub mod search;
+pub mod shuffle;
+pub mod storage;
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Perms {
     pub view_facts: bool,
     pub add_fact: bool,
     pub delete_fact: bool,
+    // Added after the others, so older configs without it still load
+    // Also allows changing a fact's tags
+    #[serde(default)]
+    pub edit_fact: bool,
+    // Viewing, restoring, and emptying deleted facts
+    #[serde(default)]
+    pub manage_trash: bool,
+    // Reloading every fact and flag from storage
+    #[serde(default)]
+    pub reload: bool,
+    // Exporting everything into a bundle, and restoring from one
+    #[serde(default)]
+    pub backup: bool,
     pub view_flags: bool,
     pub add_flag: bool,
     pub delete_flag: bool,
@@ -45,19 +69,88 @@ pub struct Config {
     pub logging_dir: String,
     pub logging_level: String,
     pub facts_dir: String,
-    pub animal_fact_types: Vec<Animal>,
+    // Where facts and flags are saved, which is JSON files in `facts_dir` unless configured otherwise
+    #[serde(default)]
+    pub storage: storage::StorageConfig,
+    // The language that each fact's base content is written in
+    #[serde(
+        default = "default_language",
+        deserialize_with = "language::deserialize_language"
+    )]
+    pub default_language: String,
+    pub animal_fact_types: Vec<AnimalType>,
+    // The most facts that can be requested at once from `/{animal}/facts`
+    #[serde(default = "default_max_batch_count")]
+    pub max_batch_count: usize,
+    // How often, in seconds, `facts_dir` is checked for changes to reload. 0 turns this off
+    #[serde(default = "default_watch_interval")]
+    pub watch_interval: u64,
+    // How new fact and flag IDs are picked
+    #[serde(default)]
+    pub id_scheme: ids::IdScheme,
+    // Sends every ID in a response as a string, for clients like JavaScript that lose precision on
+    // large numbers. Requests take IDs as either numbers or strings regardless.
+    #[serde(default)]
+    pub ids_as_strings: bool,
+    // How new facts that resemble existing ones are handled
+    #[serde(default)]
+    pub duplicates: duplicates::DuplicateConfig,
+    // Lets clients that identify themselves see every fact before any repeat
+    #[serde(default)]
+    pub shuffle: shuffle::ShuffleConfig,
+    // The timezone whose midnight picks a new fact of the day, unless the request asks for another
+    #[serde(default = "default_daily_timezone")]
+    pub daily_timezone: chrono_tz::Tz,
+    // Multiplied into a fact's weight once for each open flag on it, so flagged facts come up less.
+    // The default of 1 leaves weights alone
+    #[serde(
+        default = "default_flagged_weight",
+        deserialize_with = "animal_facts::deserialize_flagged_weight"
+    )]
+    pub flagged_weight: f64,
     pub flagging_enabled: bool,
     pub flaggers: Vec<Flagger>,
     pub server: ServerConfig,
     pub admins: Vec<Admin>,
 }
 
+fn default_language() -> String {
+    "en".to_string()
+}
+
+fn default_max_batch_count() -> usize {
+    50
+}
+
+fn default_watch_interval() -> u64 {
+    5
+}
+
+fn default_daily_timezone() -> chrono_tz::Tz {
+    chrono_tz::UTC
+}
+
+fn default_flagged_weight() -> f64 {
+    1.0
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AdminFactRequest {
-    // Only used on removals
+    // Only used on removals/updates
+    #[serde(default, deserialize_with = "ids::deserialize_optional_id")]
     pub fact_id: Option<u64>,
     // Only used on additions/updates
     pub fact_content: Option<String>,
+    // Only used on additions/updates
+    pub fact_source: Option<String>,
+    // Used on additions and when adding or removing tags
+    pub fact_tags: Option<Vec<String>>,
+    // Used on additions and when setting a fact's weight
+    pub fact_weight: Option<f64>,
+    // Only used when setting or deleting a translation
+    pub language: Option<String>,
+    // Only used when reverting, counting from 0 for the oldest revision
+    pub revision: Option<usize>,
     pub animal_type: Animal,
     pub key: String,
 }
@@ -66,6 +159,21 @@ pub struct AdminFactRequest {
 pub enum AdminAction {
     Add,
     Delete,
+    Update,
+    Tag,
+    Untag,
+    SetTranslation,
+    DeleteTranslation,
+    ViewTrash,
+    Restore,
+    EmptyTrash,
+    History,
+    Revert,
+    Weigh,
+    Reload,
+    Export,
+    RestoreBundle,
+    Duplicates,
     View,
 }
 
@@ -74,15 +182,60 @@ impl fmt::Display for AdminAction {
         match self {
             AdminAction::Add => write!(f, "add"),
             AdminAction::Delete => write!(f, "delete"),
+            AdminAction::Update => write!(f, "update"),
+            AdminAction::Tag => write!(f, "tag"),
+            AdminAction::Untag => write!(f, "untag"),
+            AdminAction::SetTranslation => write!(f, "set a translation of"),
+            AdminAction::DeleteTranslation => write!(f, "delete a translation of"),
+            AdminAction::ViewTrash => write!(f, "view the trash of"),
+            AdminAction::Restore => write!(f, "restore"),
+            AdminAction::EmptyTrash => write!(f, "empty the trash of"),
+            AdminAction::History => write!(f, "view the history of"),
+            AdminAction::Revert => write!(f, "revert"),
+            AdminAction::Weigh => write!(f, "set the weight of"),
+            AdminAction::Reload => write!(f, "reload"),
+            AdminAction::Export => write!(f, "export"),
+            AdminAction::RestoreBundle => write!(f, "restore a bundle of"),
+            AdminAction::Duplicates => write!(f, "find duplicates in"),
             AdminAction::View => write!(f, "view"),
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AdminImportRequest {
+    pub animal_type: Animal,
+    pub format: import::ImportFormat,
+    // The field, or CSV column, holding each fact's content. Defaults to `content`
+    pub content_field: Option<String>,
+    // The full contents of the file being imported
+    pub data: String,
+    pub key: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AdminReloadRequest {
+    pub key: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AdminExportRequest {
+    pub key: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AdminRestoreRequest {
+    pub mode: bundle::RestoreMode,
+    pub bundle: bundle::Bundle,
+    pub key: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AdminFlagRequest {
     pub key: String,
+    #[serde(default, deserialize_with = "ids::deserialize_optional_id")]
     pub fact_id: Option<u64>,
+    #[serde(default, deserialize_with = "ids::deserialize_optional_id")]
     pub flag_id: Option<u64>,
     pub reason: Option<String>,
     pub fact_type: Option<Animal>,
@@ -97,6 +250,7 @@ pub struct Flagger {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct FactFlagRequest {
     pub fact_type: Animal,
+    #[serde(deserialize_with = "ids::deserialize_id")]
     pub fact_id: u64,
     pub reason: Option<String>,
     pub key: String,
@@ -106,59 +260,170 @@ pub struct FactFlagRequest {
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct FactFlag {
+    #[serde(deserialize_with = "ids::deserialize_id")]
     pub id: u64,
     pub fact_type: Animal,
+    #[serde(deserialize_with = "ids::deserialize_id")]
     pub fact_id: u64,
     pub reason: Option<String>,
     pub flagger: String,
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
-pub enum Animal {
-    Cat,
-    Dog,
-}
+// Animals are identified by the slug they are configured with, ex. `cat` in `/cat/fact`.
+// Slugs are case insensitive so older data using `Cat` and `Dog` still resolves.
+#[derive(Clone, Serialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct Animal(String);
 
 impl Animal {
-    pub fn get_filepath(self, dir: &str) -> PathBuf {
-        match self {
-            Animal::Cat => Path::new(dir).join("cat_facts.json"),
-            Animal::Dog => Path::new(dir).join("dog_facts.json"),
-        }
+    pub fn new(slug: &str) -> Self {
+        Animal(slug.to_ascii_lowercase())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-impl Animal {
-    fn as_str(self) -> &'static str {
-        match self {
-            Animal::Cat => "Cat",
-            Animal::Dog => "Dog",
+impl From<&str> for Animal {
+    fn from(slug: &str) -> Self {
+        Animal::new(slug)
+    }
+}
+
+impl<'de> Deserialize<'de> for Animal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let slug = String::deserialize(deserializer)?;
+        Ok(Animal::new(&slug))
+    }
+}
+
+impl fmt::Display for Animal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// A species that the API can serve facts for, as declared in the config
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(from = "AnimalTypeConfig")]
+pub struct AnimalType {
+    pub slug: Animal,
+    // Used for log messages, responses, and metric labels
+    pub name: String,
+    // The file inside of `facts_dir` that holds this animal's facts
+    pub file: String,
+}
+
+// Older configs only listed the names of the animals, ex. `animal_fact_types = ["Cat", "Dog"]`,
+// which still load with the slug and file those animals always had
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnimalTypeConfig {
+    Name(String),
+    Full {
+        slug: Animal,
+        name: String,
+        file: String,
+    },
+}
+
+impl From<AnimalTypeConfig> for AnimalType {
+    fn from(config: AnimalTypeConfig) -> Self {
+        match config {
+            AnimalTypeConfig::Name(name) => {
+                let slug = Animal::new(&name);
+                AnimalType {
+                    file: format!("{}_facts.json", slug),
+                    slug,
+                    name,
+                }
+            }
+            AnimalTypeConfig::Full { slug, name, file } => AnimalType { slug, name, file },
         }
     }
 }
 
+impl AnimalType {
+    pub fn get_filepath(&self, dir: &str) -> PathBuf {
+        Path::new(dir).join(&self.file)
+    }
+
+    // Deleted facts are kept next to the facts file, ex. `cat_facts.trash.json`
+    pub fn get_trash_filepath(&self, dir: &str) -> PathBuf {
+        self.get_filepath(dir).with_extension("trash.json")
+    }
+}
+
+impl Config {
+    pub fn animal_type(&self, animal: &Animal) -> Option<&AnimalType> {
+        self.animal_fact_types
+            .iter()
+            .find(|animal_type| animal_type.slug == *animal)
+    }
+}
+
 pub struct APIState {
     pub config: Config,
     pub fact_lists: animal_facts::FactLists,
     pub fact_flags: Option<RwLock<Vec<FactFlag>>>,
+    pub search_index: RwLock<search::SearchIndex>,
+    pub shuffle_bags: shuffle::ShuffleBags,
+    pub storage: Box<dyn storage::Storage>,
     pub stat_register: Registry,
     pub req_counter: IntCounterVec,
+    // How many facts are being served for each animal, and how many flags are open
+    pub fact_count: IntGaugeVec,
+    pub flag_count: IntGauge,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
-pub enum CreatedAction {
-    Fact { animal: Animal },
+impl APIState {
+    pub fn fact_exists(&self, animal: &Animal, fact_id: u64) -> bool {
+        match self.fact_lists.get(animal) {
+            Some(fact_list) => fact_list
+                .read()
+                .unwrap()
+                .iter()
+                .any(|fact| fact.id == fact_id),
+            None => false,
+        }
+    }
+
+    // Every successful JSON response goes through here, so IDs can be sent as strings if configured
+    pub fn json_response<T: Serialize>(&self, body: &T) -> HttpResponse {
+        self.json_response_with(StatusCode::OK, body)
+    }
+
+    pub fn json_response_with<T: Serialize>(&self, status: StatusCode, body: &T) -> HttpResponse {
+        if !self.config.ids_as_strings {
+            return HttpResponse::build(status).json(body);
+        }
+
+        match serde_json::to_value(body) {
+            Ok(mut value) => {
+                ids::stringify_ids(&mut value);
+                HttpResponse::build(status).json(value)
+            }
+            Err(err) => {
+                error!("Failed serializing a response: {}", err);
+                generate_response(&JsonResp::new(500, "The response couldn't be created"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CreatedAction<'a> {
+    // Holds the display name of the animal the fact was added to
+    Fact { animal: &'a str },
     Flag,
 }
 
-impl CreatedAction {
-    pub fn as_str(self) -> &'static str {
+impl CreatedAction<'_> {
+    pub fn message(self) -> Cow<'static, str> {
         match self {
-            CreatedAction::Fact { animal } => match animal {
-                Animal::Cat => "Cat fact added",
-                Animal::Dog => "Dog fact added",
-            },
-            CreatedAction::Flag => "Flag set",
+            CreatedAction::Fact { animal } => Cow::Owned(format!("{} fact added", animal)),
+            CreatedAction::Flag => Cow::Borrowed("Flag set"),
         }
     }
 }
@@ -171,31 +436,70 @@ pub const RESP_NO_CONTENT_SPECIFIED: JsonResp = JsonResp::new(400, "No content w
 pub const RESP_ID_NOT_FOUND: JsonResp = JsonResp::new(404, "The requested ID doesn't exist");
 pub const RESP_NO_TYPE_SUPPLIED: JsonResp = JsonResp::new(400, "The animal type was not specified");
 pub const RESP_NO_ID_SUPPLIED: JsonResp = JsonResp::new(400, "An ID was not specified");
+pub const RESP_NO_TAGS_SUPPLIED: JsonResp = JsonResp::new(400, "No tags were specified");
+pub const RESP_NO_LANGUAGE_SUPPLIED: JsonResp = JsonResp::new(400, "A language was not specified");
+pub const RESP_DEFAULT_LANGUAGE: JsonResp = JsonResp::new(
+    400,
+    "The default language is the fact's content, use the update endpoint instead",
+);
+pub const RESP_NO_TRANSLATION: JsonResp =
+    JsonResp::new(404, "The fact has no translation in that language");
+pub const RESP_BAD_WEIGHT: JsonResp =
+    JsonResp::new(400, "The weight must be a number that's at least 0");
+pub const RESP_NO_REVISION_SUPPLIED: JsonResp = JsonResp::new(400, "A revision was not specified");
+pub const RESP_REVISION_NOT_FOUND: JsonResp =
+    JsonResp::new(404, "The requested revision doesn't exist");
+pub const RESP_NO_QUERY: JsonResp = JsonResp::new(400, "A search query was not specified");
+pub const RESP_BAD_TIMEZONE: JsonResp = JsonResp::new(
+    400,
+    "The timezone isn't known, it should look like `Europe/Berlin`",
+);
+pub const RESP_BAD_COUNT: JsonResp = JsonResp::new(400, "The count must be at least 1");
+pub const RESP_NO_MATCHING_FACTS: JsonResp =
+    JsonResp::new(404, "No facts matched the requested filters");
+pub const RESP_SAVE_FAILED: JsonResp = JsonResp::new(
+    500,
+    "The change couldn't be saved, so it was undone. Please try again later",
+);
+pub const RESP_UNKNOWN_ANIMAL: JsonResp =
+    JsonResp::new(404, "The requested animal type doesn't exist");
+pub const RESP_TOO_SIMILAR: JsonResp =
+    JsonResp::new(409, "The fact is too similar to facts that already exist");
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct JsonResp {
     pub code: u16,
-    pub message: &'static str,
+    pub message: Cow<'static, str>,
 }
 
 impl JsonResp {
     pub const fn new(code: u16, message: &'static str) -> Self {
+        JsonResp {
+            code,
+            message: Cow::Borrowed(message),
+        }
+    }
+
+    pub fn with_message(code: u16, message: Cow<'static, str>) -> Self {
         JsonResp { code, message }
     }
 }
 
-pub fn load_fact_flags(flag_count: &IntCounter, config: &Config) -> Option<RwLock<Vec<FactFlag>>> {
-    let file_name = Path::new(&config.facts_dir).join("fact_flags.json");
-
+pub fn load_fact_flags(
+    flag_count: &IntGauge,
+    config: &Config,
+    storage: &dyn storage::Storage,
+) -> Option<RwLock<Vec<FactFlag>>> {
     if config.flagging_enabled {
-        match fs::read_to_string(&file_name) {
-            Ok(contents) => {
-                let flags: Vec<FactFlag> =
-                    serde_json::from_str(&contents).expect("The flags file was malformed!");
-                flag_count.inc_by(flags.len() as u64);
+        let flags = storage
+            .load_flags()
+            .unwrap_or_else(|err| panic!("The flags couldn't be loaded: {}", err));
+        match flags {
+            Some(flags) => {
+                flag_count.set(flags.len() as i64);
                 Some(RwLock::new(flags))
             }
-            Err(_) => {
+            None => {
                 warn!("Fact flagging was enabled, but the flagging history couldn't be found!");
                 None
             }
@@ -205,36 +509,42 @@ pub fn load_fact_flags(flag_count: &IntCounter, config: &Config) -> Option<RwLoc
     }
 }
 
-pub fn load_fact_lists(fact_count: &IntCounterVec, config: &Config) -> animal_facts::FactLists {
+pub fn load_fact_lists(
+    fact_count: &IntGaugeVec,
+    config: &Config,
+    storage: &dyn storage::Storage,
+) -> animal_facts::FactLists {
     let mut fact_lists = animal_facts::FactLists::default();
     for animal in &config.animal_fact_types {
-        let file_name = animal.get_filepath(&config.facts_dir);
-
-        if let Ok(fact_file) = fs::read_to_string(file_name) {
-            let facts: Vec<animal_facts::Fact> = serde_json::from_str(&fact_file).unwrap();
-
+        let facts = storage
+            .load_facts(animal)
+            .unwrap_or_else(|err| panic!("The {} facts couldn't be loaded: {}", animal.name, err));
+        if let Some(facts) = facts {
             if facts.is_empty() {
                 warn!(
                     "While loading {} facts, none were found in the file!",
-                    animal.as_str()
+                    animal.name
                 );
+                fact_lists.insert_unloaded(animal.slug.clone());
                 continue;
             }
 
             fact_count
-                .with_label_values(&[animal.as_str()])
-                .inc_by(facts.len() as u64);
+                .with_label_values(&[&animal.name])
+                .set(facts.len() as i64);
 
-            info!("{} facts loaded!", animal.as_str());
-            match animal {
-                Animal::Cat => fact_lists.cat_facts = Some(RwLock::new(facts)),
-                Animal::Dog => fact_lists.dog_facts = Some(RwLock::new(facts)),
-            }
+            let trash = storage.load_trash(animal).unwrap_or_else(|err| {
+                panic!("The {} trash couldn't be loaded: {}", animal.name, err)
+            });
+
+            info!("{} facts loaded!", animal.name);
+            fact_lists.insert(animal.slug.clone(), facts, trash);
         } else {
             warn!(
                 "The facts file for the {} facts couldn't be found!",
-                animal.as_str()
+                animal.name
             );
+            fact_lists.insert_unloaded(animal.slug.clone());
         }
     }
 
@@ -244,16 +554,18 @@ pub fn load_fact_lists(fact_count: &IntCounterVec, config: &Config) -> animal_fa
 pub fn generate_response(resp: &JsonResp) -> HttpResponse {
     let status = StatusCode::from_u16(resp.code).unwrap();
 
-    if status.is_server_error() {
+    if status == StatusCode::NOT_IMPLEMENTED {
         warn!("A request to an unloaded part of the server occured!")
     }
 
     match status {
         StatusCode::CREATED => HttpResponse::Created().json(resp),
         StatusCode::NOT_IMPLEMENTED => HttpResponse::NotImplemented().json(resp),
+        StatusCode::INTERNAL_SERVER_ERROR => HttpResponse::InternalServerError().json(resp),
         StatusCode::UNAUTHORIZED => HttpResponse::Unauthorized().json(resp),
         StatusCode::BAD_REQUEST => HttpResponse::BadRequest().json(resp),
         StatusCode::NOT_FOUND => HttpResponse::NotFound().json(resp),
+        StatusCode::CONFLICT => HttpResponse::Conflict().json(resp),
         _ => unreachable!(),
     }
 }
@@ -0,0 +1,288 @@
+use actix_web::web::Data;
+use log::{error, info};
+use serde::Serialize;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::animal_facts::AnimalLists;
+use crate::search::SearchIndex;
+use crate::{APIState, Animal, AnimalType};
+
+// What is loaded after a successful reload
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ReloadSummary {
+    // How many facts are being served for each animal
+    pub facts: BTreeMap<Animal, usize>,
+    // Left out when flagging isn't enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<usize>,
+    // False if nothing differed from what was already loaded
+    pub changed: bool,
+}
+
+// Loads every animal's facts, and the flags, from storage again. Everything is validated before any of it
+// is swapped in, and if anything is wrong the current data is kept and the problems are returned.
+pub fn reload_facts(state: &APIState) -> Result<ReloadSummary, Vec<String>> {
+    let mut animals: Vec<&AnimalType> = state
+        .config
+        .animal_fact_types
+        .iter()
+        .filter(|animal| state.fact_lists.get_unloaded(&animal.slug).is_some())
+        .collect();
+    animals.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+    // Every list is held until the reload is finished, so no change made through the API is lost in between
+    let entries: Vec<_> = animals
+        .iter()
+        .map(|animal| state.fact_lists.get_unloaded(&animal.slug).unwrap())
+        .collect();
+    let mut list_locks: Vec<_> = entries
+        .iter()
+        .map(|(list, _)| list.write().unwrap())
+        .collect();
+    let mut trash_locks: Vec<_> = entries
+        .iter()
+        .map(|(_, trash)| trash.write().unwrap())
+        .collect();
+    let mut flag_lock = state
+        .fact_flags
+        .as_ref()
+        .map(|flags| flags.write().unwrap());
+
+    let mut errors = Vec::new();
+    let mut reloaded: Vec<Option<AnimalLists>> = Vec::with_capacity(animals.len());
+    for animal in &animals {
+        match load_animal(state, animal) {
+            Ok(None) if state.fact_lists.get(&animal.slug).is_some() => {
+                errors.push(format!("The {} facts are missing or empty", animal.name));
+            }
+            Ok(lists) => reloaded.push(lists),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    let flags = match &flag_lock {
+        Some(_) => match state.storage.load_flags() {
+            Ok(Some(flags)) => {
+                errors.extend(duplicate_ids("flag", flags.iter().map(|flag| flag.id)));
+                Some(flags)
+            }
+            Ok(None) => {
+                errors.push("The flags are missing".to_string());
+                None
+            }
+            Err(err) => {
+                errors.push(format!("Couldn't load the flags: {}", err));
+                None
+            }
+        },
+        None => None,
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut changed = flag_lock.as_deref() != flags.as_ref();
+    for (i, animal) in animals.iter().enumerate() {
+        changed |= match &reloaded[i] {
+            Some((facts, trash)) => {
+                state.fact_lists.get(&animal.slug).is_none()
+                    || *list_locks[i] != *facts
+                    || *trash_locks[i] != *trash
+            }
+            None => false,
+        };
+    }
+
+    if changed {
+        let mut index = SearchIndex::default();
+        for (i, animal) in animals.iter().enumerate() {
+            if let Some((facts, trash)) = reloaded[i].take() {
+                index.insert_all(&animal.slug, &facts);
+                state
+                    .fact_count
+                    .with_label_values(&[&animal.name])
+                    .set(facts.len() as i64);
+                *list_locks[i] = facts;
+                *trash_locks[i] = trash;
+                state.fact_lists.set_loaded(animal.slug.clone(), true);
+            }
+        }
+        if let (Some(flag_lock), Some(flags)) = (flag_lock.as_mut(), flags) {
+            state.flag_count.set(flags.len() as i64);
+            **flag_lock = flags;
+        }
+
+        *state.search_index.write().unwrap() = index;
+    }
+
+    Ok(ReloadSummary {
+        facts: animals
+            .iter()
+            .enumerate()
+            .filter(|(_, animal)| state.fact_lists.get(&animal.slug).is_some())
+            .map(|(i, animal)| (animal.slug.clone(), list_locks[i].len()))
+            .collect(),
+        flags: flag_lock.map(|flags| flags.len()),
+        changed,
+    })
+}
+
+// Loads and validates a single animal, where nothing to serve is treated the same as at startup
+fn load_animal(state: &APIState, animal: &AnimalType) -> Result<Option<AnimalLists>, String> {
+    let facts = match state.storage.load_facts(animal) {
+        Ok(Some(facts)) if !facts.is_empty() => facts,
+        Ok(_) => return Ok(None),
+        Err(err) => return Err(format!("Couldn't load the {} facts: {}", animal.name, err)),
+    };
+    let trash = state
+        .storage
+        .load_trash(animal)
+        .map_err(|err| format!("Couldn't load the {} trash: {}", animal.name, err))?;
+
+    let mut problems = duplicate_ids(
+        &format!("{} fact", animal.name),
+        facts
+            .iter()
+            .chain(trash.iter().map(|trashed| &trashed.fact))
+            .map(|fact| fact.id),
+    );
+    problems.extend(
+        facts
+            .iter()
+            .filter(|fact| fact.content.trim().is_empty())
+            .map(|fact| format!("{} fact #{} has no content", animal.name, fact.id)),
+    );
+
+    if problems.is_empty() {
+        Ok(Some((facts, trash)))
+    } else {
+        Err(problems.join(", "))
+    }
+}
+
+pub(crate) fn duplicate_ids(kind: &str, ids: impl Iterator<Item = u64>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    ids.filter(|id| !seen.insert(*id))
+        .map(|id| format!("More than one {} has the ID {}", kind, id))
+        .collect()
+}
+
+// Whether storage has anything that differs from what's being served. Only one read lock is held at a
+// time, so unlike a reload this doesn't hold up requests. Anything that can't be loaded counts as a
+// difference, so the reload that follows reports it.
+pub fn storage_differs(state: &APIState) -> bool {
+    for animal in &state.config.animal_fact_types {
+        if state.fact_lists.get_unloaded(&animal.slug).is_none() {
+            continue;
+        }
+
+        let current = state
+            .fact_lists
+            .get(&animal.slug)
+            .zip(state.fact_lists.get_trash(&animal.slug));
+        let differs = match (load_animal(state, animal), current) {
+            (Ok(Some((facts, trash))), Some((list, current_trash))) => {
+                *list.read().unwrap() != facts || *current_trash.read().unwrap() != trash
+            }
+            (Ok(None), None) => false,
+            _ => true,
+        };
+        if differs {
+            return true;
+        }
+    }
+
+    match &state.fact_flags {
+        Some(current) => match state.storage.load_flags() {
+            Ok(Some(flags)) => *current.read().unwrap() != flags,
+            _ => true,
+        },
+        None => false,
+    }
+}
+
+fn log_reload(state: &APIState, reason: &str) {
+    match reload_facts(state) {
+        Ok(summary) if summary.changed => info!("Facts reloaded after {}", reason),
+        Ok(_) => {}
+        Err(errors) => error!(
+            "Facts weren't reloaded after {}, since the new data was invalid: {}",
+            reason,
+            errors.join("; ")
+        ),
+    }
+}
+
+// Checks the fact and flag files in `facts_dir` for changes every `interval`, and reloads whenever they
+// hold something other than what's being served. The server's own saves change the files too, but
+// since those match what's loaded, they don't cause a reload.
+pub fn watch_facts_dir(state: Data<APIState>, interval: Duration) {
+    thread::spawn(move || {
+        let files = watched_files(&state);
+        let mut last_seen = files_state(&files);
+        loop {
+            thread::sleep(interval);
+
+            let current = files_state(&files);
+            if current != last_seen {
+                if storage_differs(&state) {
+                    log_reload(&state, "a file changed");
+                }
+                last_seen = current;
+            }
+        }
+    });
+}
+
+// The files that can be edited by hand. Everything else the server keeps in `facts_dir`, such as the
+// journal, is only ever written by the server itself.
+fn watched_files(state: &APIState) -> Vec<PathBuf> {
+    let facts_dir = &state.config.facts_dir;
+    let mut files: Vec<PathBuf> = state
+        .config
+        .animal_fact_types
+        .iter()
+        .flat_map(|animal| {
+            [
+                animal.get_filepath(facts_dir),
+                animal.get_trash_filepath(facts_dir),
+            ]
+        })
+        .collect();
+    files.push(Path::new(facts_dir).join("fact_flags.json"));
+    files
+}
+
+// The size and modification time of each file, which are missing for files that don't exist
+fn files_state(files: &[PathBuf]) -> BTreeMap<&Path, Option<(u64, Option<SystemTime>)>> {
+    files
+        .iter()
+        .map(|file| {
+            let metadata = fs::metadata(file).ok();
+            (
+                file.as_path(),
+                metadata.map(|metadata| (metadata.len(), metadata.modified().ok())),
+            )
+        })
+        .collect()
+}
+
+// Reloads whenever the process receives a SIGHUP
+#[cfg(unix)]
+pub fn reload_on_hangup(state: Data<APIState>) {
+    use signal_hook::consts::SIGHUP;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGHUP]).expect("Failed to listen for SIGHUP!");
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            log_reload(&state, "a SIGHUP");
+        }
+    });
+}
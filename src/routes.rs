@@ -0,0 +1,70 @@
+use actix_web::web::{self, Data, ServiceConfig};
+use prometheus::{Encoder, TextEncoder};
+
+use crate::{admin, animal_facts, bundle, daily, flagging, import, search, APIState};
+
+async fn prom_stats(app_data: Data<APIState>) -> String {
+    let register = &app_data.stat_register;
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(100);
+    let encoder = TextEncoder::new();
+
+    let metrics = register.gather();
+    encoder.encode(&metrics, &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+async fn index() -> &'static str {
+    "Hello There! This is Gearbot's animal fact API. Head over to /cat/fact or /dog/fact to try it out!"
+}
+
+// Every route the server handles. Resources are matched by path before method, so the admin routes
+// come first; otherwise `/admin/fact/add` would match `/{animal}/fact/{id}` and get a 405.
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/admin/fact/list").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/add").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/delete").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/update").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/history").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/revert").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/weight").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/duplicates").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/tag/add").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/fact/tag/remove").route(web::post().to(admin::modify_fact)))
+        .service(
+            web::resource("/admin/fact/translation/set").route(web::post().to(admin::modify_fact)),
+        )
+        .service(
+            web::resource("/admin/fact/translation/delete")
+                .route(web::post().to(admin::modify_fact)),
+        )
+        .service(web::resource("/admin/trash/list").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/trash/restore").route(web::post().to(admin::modify_fact)))
+        .service(web::resource("/admin/trash/empty").route(web::post().to(admin::modify_fact)))
+        .service(
+            web::resource("/admin/fact/import")
+                .app_data(web::JsonConfig::default().limit(import::MAX_IMPORT_SIZE))
+                .route(web::post().to(admin::import_facts)),
+        )
+        .service(web::resource("/admin/export").route(web::post().to(admin::export)))
+        .service(
+            web::resource("/admin/restore")
+                .app_data(web::JsonConfig::default().limit(bundle::MAX_BUNDLE_SIZE))
+                .route(web::post().to(admin::restore_bundle)),
+        )
+        .service(web::resource("/admin/reload").route(web::post().to(admin::reload)))
+        .service(web::resource("/admin/flag/list").route(web::post().to(admin::modify_flag)))
+        .service(web::resource("/admin/flag/add").route(web::post().to(admin::modify_flag)))
+        .service(web::resource("/admin/flag/delete").route(web::post().to(admin::modify_flag)))
+        .service(web::resource("/fact").route(web::get().to(animal_facts::get_any_fact)))
+        .service(web::resource("/{animal}/fact").route(web::get().to(animal_facts::get_fact)))
+        .service(web::resource("/{animal}/facts").route(web::get().to(animal_facts::get_facts)))
+        .service(web::resource("/{animal}/fact/daily").route(web::get().to(daily::get_daily_fact)))
+        .service(
+            web::resource("/{animal}/fact/{id}").route(web::get().to(animal_facts::get_fact_by_id)),
+        )
+        .service(web::resource("/search").route(web::get().to(search::search_facts)))
+        .service(web::resource("/flag").route(web::post().to(flagging::set_flag)))
+        .service(web::resource("/").to(index))
+        .service(web::resource("/metrics").to(prom_stats));
+}
@@ -0,0 +1,256 @@
+use actix_web::web::{Data, HttpResponse, Query};
+use actix_web::HttpRequest;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::animal_facts::{Fact, FactLists, PublicFact};
+use crate::language::requested_languages;
+use crate::{generate_response, APIState, Animal, Config, RESP_NO_QUERY, RESP_UNKNOWN_ANIMAL};
+
+type DocKey = (Animal, u64);
+
+// An inverted index over the words in every loaded fact and its translations
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    // Maps each word to the facts containing it, and how many times it appears in each
+    postings: HashMap<String, HashMap<DocKey, u32>>,
+    // The words indexed for each fact, so it can be removed again later
+    documents: HashMap<DocKey, Vec<String>>,
+}
+
+impl SearchIndex {
+    pub fn new(fact_lists: &FactLists, config: &Config) -> Self {
+        let mut index = SearchIndex::default();
+
+        for animal in &config.animal_fact_types {
+            if let Some(fact_list) = fact_lists.get(&animal.slug) {
+                index.insert_all(&animal.slug, &fact_list.read().unwrap());
+            }
+        }
+
+        index
+    }
+
+    pub fn insert_all(&mut self, animal: &Animal, facts: &[Fact]) {
+        for fact in facts {
+            self.insert(animal, fact);
+        }
+    }
+
+    // Indexes a fact, replacing anything that was previously indexed under its ID
+    pub fn insert(&mut self, animal: &Animal, fact: &Fact) {
+        let key = (animal.clone(), fact.id);
+        self.remove(animal, fact.id);
+
+        let words: Vec<String> = std::iter::once(fact.content.as_str())
+            .chain(fact.translations.values().map(String::as_str))
+            .flat_map(tokenize)
+            .collect();
+
+        for word in &words {
+            *self
+                .postings
+                .entry(word.clone())
+                .or_default()
+                .entry(key.clone())
+                .or_insert(0) += 1;
+        }
+
+        self.documents.insert(key, words);
+    }
+
+    pub fn remove(&mut self, animal: &Animal, id: u64) {
+        let key = (animal.clone(), id);
+
+        if let Some(words) = self.documents.remove(&key) {
+            for word in words {
+                if let Some(docs) = self.postings.get_mut(&word) {
+                    docs.remove(&key);
+                    if docs.is_empty() {
+                        self.postings.remove(&word);
+                    }
+                }
+            }
+        }
+    }
+
+    // Ranks facts by how many of the query's words they contain, then by TF-IDF so that
+    // rarer words count for more and long facts don't win just by being long.
+    pub fn search(&self, query: &str, animal: Option<&Animal>) -> Vec<(DocKey, f64)> {
+        let terms: HashSet<String> = tokenize(query).collect();
+        let total_docs = self.documents.len() as f64;
+
+        let mut scores: HashMap<&DocKey, (usize, f64)> = HashMap::new();
+        for term in &terms {
+            let docs = match self.postings.get(term) {
+                Some(docs) => docs,
+                None => continue,
+            };
+            let idf = (1.0 + total_docs / docs.len() as f64).ln();
+
+            for (key, count) in docs {
+                if animal.is_some_and(|animal| *animal != key.0) {
+                    continue;
+                }
+
+                let length = self.documents[key].len() as f64;
+                let entry = scores.entry(key).or_insert((0, 0.0));
+                entry.0 += 1;
+                entry.1 += *count as f64 * idf / length.sqrt();
+            }
+        }
+
+        let mut ranked: Vec<(&DocKey, (usize, f64))> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| {
+            (b.1).0.cmp(&(a.1).0).then_with(|| {
+                (b.1)
+                    .1
+                    .partial_cmp(&(a.1).1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+
+        ranked
+            .into_iter()
+            .map(|(key, (_, score))| (key.clone(), score))
+            .collect()
+    }
+}
+
+// Splits text into lowercase words and strips simple plurals and possessives,
+// so that searching "cat" also finds "Cats" and "cat's".
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| {
+            let word = word.trim_matches('\'').to_lowercase();
+            let word = word.strip_suffix("'s").unwrap_or(&word).to_string();
+
+            if word.chars().count() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+                word[..word.len() - 1].to_string()
+            } else {
+                word
+            }
+        })
+        .filter(|word| !word.is_empty())
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    // Limits the search to a single animal
+    pub animal: Option<Animal>,
+    pub limit: Option<usize>,
+    pub lang: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchResult<'a> {
+    pub animal: Animal,
+    pub score: f64,
+    pub fact: PublicFact<'a>,
+}
+
+pub fn search_facts(
+    app_data: Data<APIState>,
+    req: HttpRequest,
+    query: Query<SearchQuery>,
+) -> HttpResponse {
+    let search = match query.q.as_deref() {
+        Some(search) if tokenize(search).next().is_some() => search,
+        _ => return generate_response(&RESP_NO_QUERY),
+    };
+
+    if let Some(animal) = &query.animal {
+        if app_data.config.animal_type(animal).is_none() {
+            return generate_response(&RESP_UNKNOWN_ANIMAL);
+        }
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(10)
+        .clamp(1, app_data.config.max_batch_count);
+
+    // The index lock is released before any fact list is locked, since admin
+    // changes lock them in the opposite order.
+    let ranked = app_data
+        .search_index
+        .read()
+        .unwrap()
+        .search(search, query.animal.as_ref());
+
+    // Sorted so the lists are locked in the same order as everywhere else
+    let animals: BTreeSet<&Animal> = ranked.iter().map(|((animal, _), _)| animal).collect();
+    let list_locks: HashMap<&Animal, _> = animals
+        .into_iter()
+        .filter_map(|animal| {
+            app_data
+                .fact_lists
+                .get(animal)
+                .map(|list| (animal, list.read().unwrap()))
+        })
+        .collect();
+
+    // Hits for facts that are gone are skipped before the limit, so they don't take the place of real ones
+    let languages = requested_languages(&req, query.lang.as_deref());
+    let results: Vec<SearchResult> = ranked
+        .iter()
+        .filter_map(|((animal, id), score)| {
+            let fact = list_locks.get(animal)?.iter().find(|fact| fact.id == *id)?;
+
+            Some(SearchResult {
+                animal: animal.clone(),
+                score: *score,
+                fact: PublicFact::localized(fact, &languages, &app_data.config.default_language),
+            })
+        })
+        .take(limit)
+        .collect();
+
+    app_data.json_response(&results)
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::{tokenize, SearchIndex};
+    use crate::animal_facts::Fact;
+    use crate::Animal;
+
+    #[test]
+    fn tokenizing() {
+        let words: Vec<String> = tokenize("Cats' whiskers, a cat's GLASS-like eyes!").collect();
+        assert_eq!(
+            words,
+            vec!["cat", "whisker", "a", "cat", "glass", "like", "eye"]
+        );
+    }
+
+    #[test]
+    fn ranking_and_removal() {
+        let cat = Animal::new("cat");
+        let dog = Animal::new("dog");
+        let mut index = SearchIndex::default();
+        index.insert(&cat, &Fact::new(1, "Cats sleep for most of the day."));
+        index.insert(
+            &cat,
+            &Fact::new(2, "A cat's whiskers help it sleep in the dark."),
+        );
+        index.insert(&dog, &Fact::new(3, "Dogs sleep less than cats."));
+
+        let ids: Vec<u64> = index
+            .search("whisker SLEEP", None)
+            .into_iter()
+            .map(|((_, id), _)| id)
+            .collect();
+        // The fact with both words comes first
+        assert_eq!(ids[0], 2);
+        assert_eq!(ids.len(), 3);
+
+        let dog_only = index.search("sleep", Some(&dog));
+        assert_eq!(dog_only.len(), 1);
+
+        index.remove(&cat, 2);
+        assert!(index.search("whiskers", None).is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+use actix_web::web::Data;
+use log::{error, warn};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::animal_facts::Fact;
+use crate::storage::{PendingWrite, StorageError};
+use crate::{APIState, Animal};
+
+// Longer client IDs are ignored, so a client can't use up memory with huge ones
+pub const MAX_CLIENT_ID_LEN: usize = 128;
+
+// Lets clients go through every fact in a random order before any of them repeat
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShuffleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    // The header a client is identified by, when it doesn't use the `client` query parameter
+    #[serde(default = "default_header")]
+    pub header: String,
+    // How many clients are remembered. Past this, the one that went the longest without a fact is forgotten
+    #[serde(default = "default_max_clients")]
+    pub max_clients: usize,
+    // How many bags, one for each animal and tag, a client can have. Past this, the one used longest ago is dropped
+    #[serde(default = "default_max_bags")]
+    pub max_bags: usize,
+    // Where every client's progress is kept while the server isn't running. Nothing is kept when this isn't set
+    #[serde(default)]
+    pub file: Option<String>,
+    // How often, in seconds, the progress is saved while running, so a crash doesn't lose all of it.
+    // It's always saved when the server stops, and 0 only saves then.
+    #[serde(default = "default_save_interval")]
+    pub save_interval: u64,
+}
+
+impl Default for ShuffleConfig {
+    fn default() -> Self {
+        ShuffleConfig {
+            enabled: false,
+            header: default_header(),
+            max_clients: default_max_clients(),
+            max_bags: default_max_bags(),
+            file: None,
+            save_interval: default_save_interval(),
+        }
+    }
+}
+
+fn default_header() -> String {
+    "X-Client-Id".to_string()
+}
+
+fn default_max_clients() -> usize {
+    10_000
+}
+
+fn default_max_bags() -> usize {
+    32
+}
+
+fn default_save_interval() -> u64 {
+    300
+}
+
+// The facts a client hasn't been given yet, keyed by the animal and filter they came from.
+// Bags are kept in the order they were last used, with the most recent last.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Bags(Vec<(String, Vec<u64>)>);
+
+impl Bags {
+    // Gets the bag for the key and marks it as the most recently used, dropping the oldest bag if needed
+    fn bag(&mut self, key: String, max_bags: usize) -> &mut Vec<u64> {
+        match self.0.iter().position(|(bag_key, _)| *bag_key == key) {
+            Some(pos) => {
+                let bag = self.0.remove(pos);
+                self.0.push(bag);
+            }
+            None => {
+                let excess = (self.0.len() + 1).saturating_sub(max_bags.max(1));
+                self.0.drain(..excess);
+                self.0.push((key, Vec::new()));
+            }
+        }
+        &mut self.0.last_mut().unwrap().1
+    }
+}
+
+// How the bags are saved, with clients and their bags in the order they were last used
+#[derive(Serialize, Deserialize)]
+struct SavedClient {
+    client: String,
+    bags: Bags,
+}
+
+#[derive(Debug, Default)]
+struct Clients {
+    // Counts up on every draw, to tell which client was used longest ago
+    tick: u64,
+    bags: HashMap<String, (u64, Bags)>,
+    by_use: BTreeMap<u64, String>,
+}
+
+impl Clients {
+    // Gets a client's bags and marks it as the most recently used, forgetting the oldest client if needed
+    fn touch(&mut self, client: &str, max_clients: usize) -> &mut Bags {
+        self.tick += 1;
+        let tick = self.tick;
+
+        match self.bags.get_mut(client) {
+            Some((last_used, _)) => {
+                self.by_use.remove(last_used);
+                *last_used = tick;
+            }
+            None => {
+                while self.bags.len() >= max_clients.max(1) {
+                    let oldest = match self.by_use.pop_first() {
+                        Some((_, oldest)) => oldest,
+                        None => break,
+                    };
+                    self.bags.remove(&oldest);
+                }
+                self.bags
+                    .insert(client.to_string(), (tick, Bags::default()));
+            }
+        }
+        self.by_use.insert(tick, client.to_string());
+
+        &mut self.bags.get_mut(client).unwrap().1
+    }
+}
+
+#[derive(Debug)]
+pub struct ShuffleBags {
+    max_clients: usize,
+    max_bags: usize,
+    file: Option<String>,
+    clients: Mutex<Clients>,
+}
+
+impl ShuffleBags {
+    // Starts from the saved bags if there are any, or with no clients otherwise
+    pub fn new(config: &ShuffleConfig) -> Self {
+        let bags = ShuffleBags {
+            max_clients: config.max_clients,
+            max_bags: config.max_bags,
+            file: config.file.clone(),
+            clients: Mutex::new(Clients::default()),
+        };
+
+        if let Some(file) = &bags.file {
+            let saved = fs::read_to_string(file)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| {
+                    serde_json::from_str::<Vec<SavedClient>>(&contents)
+                        .map_err(|err| err.to_string())
+                });
+            match saved {
+                Ok(saved) => {
+                    let mut clients = bags.clients.lock().unwrap();
+                    for client in saved {
+                        *clients.touch(&client.client, bags.max_clients) = client.bags;
+                    }
+                }
+                Err(err) => warn!("The shuffle bags in {} couldn't be loaded: {}", file, err),
+            }
+        }
+
+        bags
+    }
+
+    // Writes every client's bags to the configured file, if there is one. Like the stored facts, the file
+    // is replaced all at once, so a crash while saving leaves the last save intact.
+    pub fn save(&self) -> Result<(), StorageError> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let saved: Vec<SavedClient> = {
+            let clients = self.clients.lock().unwrap();
+            clients
+                .by_use
+                .values()
+                .map(|client| SavedClient {
+                    client: client.clone(),
+                    bags: clients.bags[client].1.clone(),
+                })
+                .collect()
+        };
+        PendingWrite::prepare(PathBuf::from(file), &saved)?.commit()
+    }
+
+    // Draws up to `count` different facts from the client's bag for the animal and tag. When the bag runs
+    // out, it's refilled with every matching fact in a new random order.
+    pub fn draw<'a>(
+        &self,
+        client: &str,
+        animal: &Animal,
+        tag: Option<&str>,
+        matching: &[&'a Fact],
+        count: usize,
+    ) -> Vec<&'a Fact> {
+        let by_id: HashMap<u64, &Fact> = matching.iter().map(|fact| (fact.id, *fact)).collect();
+        let key = match tag {
+            Some(tag) => format!("{}?tag={}", animal, tag),
+            None => animal.to_string(),
+        };
+        let count = count.min(by_id.len());
+        // Nothing is remembered for filters that don't match anything, so they can't be used to fill up memory
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let mut clients = self.clients.lock().unwrap();
+        let bag = clients
+            .touch(client, self.max_clients)
+            .bag(key, self.max_bags);
+
+        let mut picked = HashSet::new();
+        let mut picks = Vec::with_capacity(count);
+        while picks.len() < count {
+            let id = match bag.pop() {
+                Some(id) => id,
+                None => {
+                    // Facts picked by this draw are left out, so a batch never has the same fact twice
+                    *bag = by_id
+                        .keys()
+                        .filter(|id| !picked.contains(*id))
+                        .copied()
+                        .collect();
+                    bag.sort_unstable();
+                    bag.shuffle(&mut rand::thread_rng());
+                    continue;
+                }
+            };
+
+            // Facts deleted since the bag was filled are skipped
+            if let Some(fact) = by_id.get(&id) {
+                if picked.insert(id) {
+                    picks.push(*fact);
+                }
+            }
+        }
+
+        picks
+    }
+}
+
+// Saves the bags every `interval` while the server runs
+pub fn save_periodically(state: Data<APIState>, interval: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+
+        if let Err(err) = state.shuffle_bags.save() {
+            error!("Failed saving the shuffle bags: {}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod shuffle_tests {
+    use super::*;
+
+    fn facts(count: u64) -> Vec<Fact> {
+        (1..=count)
+            .map(|id| Fact::new(id, &format!("Fact {}", id)))
+            .collect()
+    }
+
+    #[test]
+    fn no_repeats_until_empty() {
+        let facts = facts(5);
+        let matching: Vec<&Fact> = facts.iter().collect();
+        let bags = ShuffleBags::new(&ShuffleConfig::default());
+        let cat = Animal::from("cat");
+
+        let mut seen = HashSet::new();
+        for _ in 0..5 {
+            let pick = bags.draw("guild", &cat, None, &matching, 1);
+            assert!(seen.insert(pick[0].id));
+        }
+
+        // A batch larger than what's left spans two bags without repeating itself
+        let batch = bags.draw("guild", &cat, None, &matching, 4);
+        let ids: HashSet<u64> = batch.iter().map(|fact| fact.id).collect();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn oldest_client_forgotten() {
+        let facts = facts(3);
+        let matching: Vec<&Fact> = facts.iter().collect();
+        let config = ShuffleConfig {
+            max_clients: 2,
+            ..ShuffleConfig::default()
+        };
+        let bags = ShuffleBags::new(&config);
+        let cat = Animal::from("cat");
+
+        for client in ["a", "b", "a", "c"] {
+            bags.draw(client, &cat, None, &matching, 1);
+        }
+
+        let clients = bags.clients.lock().unwrap();
+        let mut remembered: Vec<&str> = clients.bags.keys().map(String::as_str).collect();
+        remembered.sort_unstable();
+        assert_eq!(remembered, ["a", "c"]);
+    }
+
+    #[test]
+    fn oldest_bag_dropped() {
+        let facts = facts(3);
+        let matching: Vec<&Fact> = facts.iter().collect();
+        let config = ShuffleConfig {
+            max_bags: 2,
+            ..ShuffleConfig::default()
+        };
+        let bags = ShuffleBags::new(&config);
+        let cat = Animal::from("cat");
+
+        for tag in ["a", "b", "a", "c"] {
+            bags.draw("guild", &cat, Some(tag), &matching, 1);
+        }
+        // Tags that match nothing don't get a bag
+        bags.draw("guild", &cat, Some("d"), &[], 1);
+
+        let clients = bags.clients.lock().unwrap();
+        let (_, guild) = &clients.bags["guild"];
+        let keys: Vec<&str> = guild.0.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["cat?tag=a", "cat?tag=c"]);
+    }
+
+    #[test]
+    fn bag_order_saved() {
+        let facts = facts(3);
+        let matching: Vec<&Fact> = facts.iter().collect();
+        let dir = tempdir::TempDir::new("shuffle").unwrap();
+        let config = ShuffleConfig {
+            max_bags: 3,
+            file: Some(dir.path().join("bags.json").to_str().unwrap().to_string()),
+            ..ShuffleConfig::default()
+        };
+        let cat = Animal::from("cat");
+
+        let bags = ShuffleBags::new(&config);
+        for tag in ["b", "a"] {
+            bags.draw("guild", &cat, Some(tag), &matching, 1);
+        }
+        bags.save().unwrap();
+
+        // After loading, `b` is still the bag used longest ago, so it's the one dropped
+        let bags = ShuffleBags::new(&config);
+        for tag in ["c", "d"] {
+            bags.draw("guild", &cat, Some(tag), &matching, 1);
+        }
+
+        let clients = bags.clients.lock().unwrap();
+        let (_, guild) = &clients.bags["guild"];
+        let keys: Vec<&str> = guild.0.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["cat?tag=a", "cat?tag=c", "cat?tag=d"]);
+    }
+}
@@ -0,0 +1,394 @@
+use fs2::FileExt;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::animal_facts::{Fact, TrashedFact};
+use crate::{AnimalType, Config, FactFlag};
+
+mod journal;
+mod schema;
+mod sqlite;
+pub use journal::JournalStorage;
+pub use schema::SCHEMA_VERSION;
+use schema::{Envelope, FileKind};
+pub use sqlite::SqliteStorage;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    // Pretty-printed JSON files inside of `facts_dir`
+    #[default]
+    Json,
+    // A single SQLite database, which imports the JSON files the first time it's opened
+    Sqlite,
+    // The JSON files are only rewritten when compacting, and changes are appended to a journal in between
+    Journal,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    // The SQLite database file, which defaults to `facts.sqlite3` inside of `facts_dir`
+    #[serde(default)]
+    pub database: Option<String>,
+    // How often, in seconds, the journal is compacted into the JSON files. Defaults to 5 minutes, and must be at least 1
+    #[serde(default)]
+    pub compact_interval: Option<u64>,
+    // Whether JSON files in an older layout are rewritten in the current one as soon as they're loaded,
+    // instead of the next time they change
+    #[serde(default)]
+    pub rewrite_migrated: bool,
+}
+
+// A single change made to an animal's facts. Backends are always given the full lists as well,
+// but ones that can update in place only need to apply this.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum FactChange {
+    Added(Fact),
+    // Several facts added at once by a bulk import
+    Imported(Vec<Fact>),
+    // Any edit to an existing fact, which holds the fact as it is now
+    Updated(Fact),
+    // The fact was deleted from the list and moved into the trash
+    Trashed(TrashedFact),
+    // The fact was moved out of the trash and back into the list
+    Restored(Fact),
+    TrashEmptied,
+    // Both lists were swapped out whole, such as when restoring a backup
+    Replaced {
+        facts: Vec<Fact>,
+        trash: Vec<TrashedFact>,
+    },
+}
+
+impl FactChange {
+    // Applies the change to an animal's lists. Replaying a change that was already applied
+    // leaves them the same, so a journal can safely be replayed on top of a newer snapshot.
+    pub fn apply(&self, facts: &mut Vec<Fact>, trash: &mut Vec<TrashedFact>) {
+        match self {
+            FactChange::Added(fact) | FactChange::Updated(fact) => replace_or_push(facts, fact),
+            FactChange::Imported(imported) => {
+                for fact in imported {
+                    replace_or_push(facts, fact);
+                }
+            }
+            FactChange::Trashed(trashed) => {
+                facts.retain(|fact| fact.id != trashed.fact.id);
+                trash.retain(|old| old.fact.id != trashed.fact.id);
+                trash.push(trashed.clone());
+            }
+            FactChange::Restored(fact) => {
+                trash.retain(|trashed| trashed.fact.id != fact.id);
+                replace_or_push(facts, fact);
+            }
+            FactChange::TrashEmptied => trash.clear(),
+            FactChange::Replaced {
+                facts: new_facts,
+                trash: new_trash,
+            } => {
+                *facts = new_facts.clone();
+                *trash = new_trash.clone();
+            }
+        }
+    }
+}
+
+fn replace_or_push(facts: &mut Vec<Fact>, fact: &Fact) {
+    match facts.iter_mut().find(|old| old.id == fact.id) {
+        Some(old) => *old = fact.clone(),
+        None => facts.push(fact.clone()),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum FlagChange {
+    Added(FactFlag),
+    Removed(u64),
+    // Every flag was swapped out at once
+    Replaced(Vec<FactFlag>),
+}
+
+impl FlagChange {
+    pub fn apply(&self, flags: &mut Vec<FactFlag>) {
+        match self {
+            FlagChange::Added(flag) => {
+                flags.retain(|old| old.id != flag.id);
+                flags.push(flag.clone());
+            }
+            FlagChange::Removed(id) => flags.retain(|flag| flag.id != *id),
+            FlagChange::Replaced(new_flags) => *flags = new_flags.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Malformed(serde_json::Error),
+    Sqlite(rusqlite::Error),
+    // The file was written by a newer version of the API
+    UnsupportedVersion(u32),
+    // The storage config can't be used, with why
+    BadConfig(&'static str),
+    // Something else, such as the server, is already writing to storage
+    InUse,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "storage I/O failed: {}", err),
+            StorageError::Malformed(err) => write!(f, "stored data was malformed: {}", err),
+            StorageError::Sqlite(err) => write!(f, "database error: {}", err),
+            StorageError::UnsupportedVersion(version) => write!(
+                f,
+                "stored data is schema version {}, but only up to version {} is supported",
+                version, SCHEMA_VERSION
+            ),
+            StorageError::BadConfig(reason) => write!(f, "invalid storage config: {}", reason),
+            StorageError::InUse => write!(
+                f,
+                "storage is in use, and the server has to be stopped before changing it directly"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(err: serde_json::Error) -> Self {
+        StorageError::Malformed(err)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        StorageError::Sqlite(err)
+    }
+}
+
+const LOCK_FILE: &str = ".animal_api.lock";
+
+// Held by the server and the commands that write to storage, so one can't overwrite what the other saved.
+// It's an advisory lock on a file in `facts_dir` that the OS lets go of when the process exits.
+pub struct StorageLock {
+    _file: File,
+}
+
+pub fn lock(config: &Config) -> Result<StorageLock, StorageError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(Path::new(&config.facts_dir).join(LOCK_FILE))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(StorageLock { _file: file }),
+        Err(err) if err.kind() == fs2::lock_contended_error().kind() => Err(StorageError::InUse),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Everything the API needs to load and save facts and flags, so handlers never touch files directly
+pub trait Storage: Send + Sync {
+    // Returns `None` if nothing has been stored for the animal yet
+    fn load_facts(&self, animal: &AnimalType) -> Result<Option<Vec<Fact>>, StorageError>;
+
+    fn load_trash(&self, animal: &AnimalType) -> Result<Vec<TrashedFact>, StorageError>;
+
+    // Returns `None` if no flags have been stored yet
+    fn load_flags(&self) -> Result<Option<Vec<FactFlag>>, StorageError>;
+
+    fn save_facts(
+        &self,
+        animal: &AnimalType,
+        facts: &[Fact],
+        trash: &[TrashedFact],
+        change: &FactChange,
+    ) -> Result<(), StorageError>;
+
+    fn save_flags(&self, flags: &[FactFlag], change: &FlagChange) -> Result<(), StorageError>;
+}
+
+pub fn open(config: &Config) -> Result<Box<dyn Storage>, StorageError> {
+    match config.storage.backend {
+        StorageBackend::Json => Ok(Box::new(JsonStorage::open(config))),
+        StorageBackend::Sqlite => {
+            let path = match &config.storage.database {
+                Some(database) => PathBuf::from(database),
+                None => Path::new(&config.facts_dir).join("facts.sqlite3"),
+            };
+            Ok(Box::new(SqliteStorage::open(&path, config)?))
+        }
+        StorageBackend::Journal => Ok(Box::new(JournalStorage::open(config)?)),
+    }
+}
+
+// Stores each animal's facts and trash in their own file, with every flag in `fact_flags.json`
+pub struct JsonStorage {
+    facts_dir: String,
+    rewrite_migrated: bool,
+}
+
+impl JsonStorage {
+    pub fn new(facts_dir: &str) -> Self {
+        JsonStorage {
+            facts_dir: facts_dir.to_string(),
+            rewrite_migrated: false,
+        }
+    }
+
+    pub fn open(config: &Config) -> Self {
+        JsonStorage {
+            facts_dir: config.facts_dir.clone(),
+            rewrite_migrated: config.storage.rewrite_migrated,
+        }
+    }
+
+    fn flags_path(&self) -> PathBuf {
+        Path::new(&self.facts_dir).join("fact_flags.json")
+    }
+}
+
+// Writes the file next to where it belongs and syncs it to disk. Nothing is replaced until
+// `commit` is called, so a crash or full disk can never leave a half-written file behind.
+pub(crate) struct PendingWrite {
+    path: PathBuf,
+    temp_path: PathBuf,
+}
+
+impl PendingWrite {
+    pub(crate) fn prepare<T: Serialize>(path: PathBuf, value: &T) -> Result<Self, StorageError> {
+        let contents = serde_json::to_string_pretty(value)?;
+
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let written = File::create(&temp_path).and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        });
+        let pending = PendingWrite { path, temp_path };
+        written?;
+
+        Ok(pending)
+    }
+
+    pub(crate) fn commit(self) -> Result<(), StorageError> {
+        fs::rename(&self.temp_path, &self.path)?;
+
+        // The rename is only durable once the directory holding it has been synced too
+        #[cfg(unix)]
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
+
+// Cleans up after a write that failed, or was never committed
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
+impl JsonStorage {
+    // Reads a JSON file in any known layout, treating a missing one as empty
+    fn read_file<T: Serialize + for<'de> Deserialize<'de>>(
+        &self,
+        path: PathBuf,
+        kind: FileKind,
+    ) -> Result<Option<Vec<T>>, StorageError> {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let (entries, version) = schema::read(&contents, kind)?;
+        if version < SCHEMA_VERSION && self.rewrite_migrated {
+            PendingWrite::prepare(path.clone(), &Envelope::new(kind, &entries))?.commit()?;
+            info!(
+                "Rewrote {} from version {} to version {}",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            );
+        }
+
+        Ok(Some(entries))
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load_facts(&self, animal: &AnimalType) -> Result<Option<Vec<Fact>>, StorageError> {
+        self.read_file(animal.get_filepath(&self.facts_dir), FileKind::Facts)
+    }
+
+    fn load_trash(&self, animal: &AnimalType) -> Result<Vec<TrashedFact>, StorageError> {
+        // The trash is optional since it won't exist until something is deleted
+        Ok(self
+            .read_file(animal.get_trash_filepath(&self.facts_dir), FileKind::Trash)?
+            .unwrap_or_default())
+    }
+
+    fn load_flags(&self) -> Result<Option<Vec<FactFlag>>, StorageError> {
+        self.read_file(self.flags_path(), FileKind::Flags)
+    }
+
+    fn save_facts(
+        &self,
+        animal: &AnimalType,
+        facts: &[Fact],
+        trash: &[TrashedFact],
+        change: &FactChange,
+    ) -> Result<(), StorageError> {
+        // Only the files that the change touched are rewritten
+        let (list_changed, trash_changed) = match change {
+            FactChange::Added(_) | FactChange::Imported(_) | FactChange::Updated(_) => {
+                (true, false)
+            }
+            FactChange::Trashed(_) | FactChange::Restored(_) | FactChange::Replaced { .. } => {
+                (true, true)
+            }
+            FactChange::TrashEmptied => (false, true),
+        };
+
+        // Both files are fully written before either is replaced, which keeps them from
+        // disagreeing about where a fact is if writing the second one fails
+        let mut pending = Vec::with_capacity(2);
+        if list_changed {
+            pending.push(PendingWrite::prepare(
+                animal.get_filepath(&self.facts_dir),
+                &Envelope::new(FileKind::Facts, facts),
+            )?);
+        }
+        if trash_changed {
+            pending.push(PendingWrite::prepare(
+                animal.get_trash_filepath(&self.facts_dir),
+                &Envelope::new(FileKind::Trash, trash),
+            )?);
+        }
+
+        pending.into_iter().try_for_each(PendingWrite::commit)
+    }
+
+    fn save_flags(&self, flags: &[FactFlag], _change: &FlagChange) -> Result<(), StorageError> {
+        PendingWrite::prepare(self.flags_path(), &Envelope::new(FileKind::Flags, flags))?.commit()
+    }
+}
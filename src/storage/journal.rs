@@ -0,0 +1,298 @@
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::schema::{Envelope, FileKind};
+use super::{FactChange, FlagChange, JsonStorage, PendingWrite, Storage, StorageError};
+use crate::animal_facts::{AnimalLists, Fact, TrashedFact};
+use crate::{Animal, AnimalType, Config, FactFlag};
+
+const DEFAULT_COMPACT_INTERVAL: u64 = 5 * 60;
+
+// One line of the journal, recording a single change and when it was made
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum JournalEvent {
+    Fact { animal: Animal, change: FactChange },
+    Flag { change: FlagChange },
+}
+
+// Keeps the JSON files as a snapshot and appends each change to `journal.jsonl` instead of
+// rewriting them. The journal is folded back into the snapshot in the background.
+pub struct JournalStorage {
+    journal: Arc<Journal>,
+}
+
+struct Journal {
+    snapshot: JsonStorage,
+    facts_dir: String,
+    animals: Vec<AnimalType>,
+    // Appends and compaction both hold this, so compaction never misses a change
+    file: Mutex<File>,
+}
+
+impl JournalStorage {
+    pub fn open(config: &Config) -> Result<Self, StorageError> {
+        let interval = config
+            .storage
+            .compact_interval
+            .unwrap_or(DEFAULT_COMPACT_INTERVAL);
+        if interval == 0 {
+            return Err(StorageError::BadConfig(
+                "compact_interval must be at least 1 second",
+            ));
+        }
+
+        let path = journal_path(&config.facts_dir);
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        let journal = Arc::new(Journal {
+            snapshot: JsonStorage::open(config),
+            facts_dir: config.facts_dir.clone(),
+            animals: config.animal_fact_types.clone(),
+            file: Mutex::new(file),
+        });
+
+        let background = Arc::downgrade(&journal);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval));
+
+            // Stops once the storage has been dropped
+            let journal = match background.upgrade() {
+                Some(journal) => journal,
+                None => break,
+            };
+            if let Err(err) = journal.compact() {
+                error!("Failed compacting the journal: {}", err);
+            }
+        });
+
+        Ok(JournalStorage { journal })
+    }
+
+    // Rewrites the JSON files with every journaled change and empties the journal
+    pub fn compact(&self) -> Result<(), StorageError> {
+        self.journal.compact()
+    }
+}
+
+fn journal_path(facts_dir: &str) -> PathBuf {
+    Path::new(facts_dir).join("journal.jsonl")
+}
+
+// Compacted entries are moved here, so the full history of changes is never lost
+fn archive_path(facts_dir: &str) -> PathBuf {
+    Path::new(facts_dir).join("journal.archive.jsonl")
+}
+
+impl Journal {
+    fn entries(&self) -> Result<Vec<JournalEntry>, StorageError> {
+        let contents = match fs::read_to_string(journal_path(&self.facts_dir)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let lines: Vec<&str> = contents.lines().filter(|line| !line.is_empty()).collect();
+        let mut entries = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // A crash in the middle of an append can only cut off the last line
+                Err(_) if i == lines.len() - 1 => {
+                    warn!("Skipping an incomplete entry at the end of the journal");
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn append(&self, event: JournalEvent) -> Result<(), StorageError> {
+        let mut line = serde_json::to_string(&JournalEntry {
+            at: Utc::now(),
+            event,
+        })?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        let start = file.seek(SeekFrom::End(0))?;
+        let written = file
+            .write_all(line.as_bytes())
+            .and_then(|_| file.sync_data());
+
+        // Cut off anything partially written, so the next entry starts on its own line
+        if let Err(err) = written {
+            let _ = file.set_len(start);
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    fn replay_facts(
+        &self,
+        animal: &AnimalType,
+        entries: &[JournalEntry],
+    ) -> Result<Option<AnimalLists>, StorageError> {
+        let changes: Vec<&FactChange> = entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                JournalEvent::Fact {
+                    animal: changed,
+                    change,
+                } if *changed == animal.slug => Some(change),
+                _ => None,
+            })
+            .collect();
+
+        let facts = self.snapshot.load_facts(animal)?;
+        if facts.is_none() && changes.is_empty() {
+            return Ok(None);
+        }
+
+        let mut facts = facts.unwrap_or_default();
+        let mut trash = self.snapshot.load_trash(animal)?;
+        for change in changes {
+            change.apply(&mut facts, &mut trash);
+        }
+
+        Ok(Some((facts, trash)))
+    }
+
+    fn replay_flags(
+        &self,
+        entries: &[JournalEntry],
+    ) -> Result<Option<Vec<FactFlag>>, StorageError> {
+        let changes: Vec<&FlagChange> = entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                JournalEvent::Flag { change } => Some(change),
+                _ => None,
+            })
+            .collect();
+
+        let flags = self.snapshot.load_flags()?;
+        if flags.is_none() && changes.is_empty() {
+            return Ok(None);
+        }
+
+        let mut flags = flags.unwrap_or_default();
+        for change in changes {
+            change.apply(&mut flags);
+        }
+
+        Ok(Some(flags))
+    }
+
+    fn compact(&self) -> Result<(), StorageError> {
+        let file = self.file.lock().unwrap();
+
+        let entries = self.entries()?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        // Every snapshot file is written before any are replaced. If the journal can't be
+        // emptied afterwards, replaying it again on top of them changes nothing.
+        let mut pending = Vec::new();
+        for animal in &self.animals {
+            if let Some((facts, trash)) = self.replay_facts(animal, &entries)? {
+                pending.push(PendingWrite::prepare(
+                    animal.get_filepath(&self.facts_dir),
+                    &Envelope::new(FileKind::Facts, &facts),
+                )?);
+                pending.push(PendingWrite::prepare(
+                    animal.get_trash_filepath(&self.facts_dir),
+                    &Envelope::new(FileKind::Trash, &trash),
+                )?);
+            }
+        }
+        if let Some(flags) = self.replay_flags(&entries)? {
+            pending.push(PendingWrite::prepare(
+                self.snapshot.flags_path(),
+                &Envelope::new(FileKind::Flags, &flags),
+            )?);
+        }
+        pending.into_iter().try_for_each(PendingWrite::commit)?;
+
+        let journal = fs::read(journal_path(&self.facts_dir))?;
+        let mut archive = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(archive_path(&self.facts_dir))?;
+        archive.write_all(&journal)?;
+        archive.sync_data()?;
+
+        file.set_len(0)?;
+        file.sync_all()?;
+
+        info!("Compacted {} journal entries", entries.len());
+        Ok(())
+    }
+}
+
+impl Storage for JournalStorage {
+    fn load_facts(&self, animal: &AnimalType) -> Result<Option<Vec<Fact>>, StorageError> {
+        let _file = self.journal.file.lock().unwrap();
+        let entries = self.journal.entries()?;
+
+        Ok(self
+            .journal
+            .replay_facts(animal, &entries)?
+            .map(|(facts, _)| facts))
+    }
+
+    fn load_trash(&self, animal: &AnimalType) -> Result<Vec<TrashedFact>, StorageError> {
+        let _file = self.journal.file.lock().unwrap();
+        let entries = self.journal.entries()?;
+
+        Ok(self
+            .journal
+            .replay_facts(animal, &entries)?
+            .map(|(_, trash)| trash)
+            .unwrap_or_default())
+    }
+
+    fn load_flags(&self) -> Result<Option<Vec<FactFlag>>, StorageError> {
+        let _file = self.journal.file.lock().unwrap();
+        let entries = self.journal.entries()?;
+
+        self.journal.replay_flags(&entries)
+    }
+
+    // Only the change is written, and the full lists are left for compaction
+    fn save_facts(
+        &self,
+        animal: &AnimalType,
+        _facts: &[Fact],
+        _trash: &[TrashedFact],
+        change: &FactChange,
+    ) -> Result<(), StorageError> {
+        self.journal.append(JournalEvent::Fact {
+            animal: animal.slug.clone(),
+            change: change.clone(),
+        })
+    }
+
+    fn save_flags(&self, _flags: &[FactFlag], change: &FlagChange) -> Result<(), StorageError> {
+        self.journal.append(JournalEvent::Flag {
+            change: change.clone(),
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use std::collections::BTreeMap;
+
+use super::StorageError;
+
+// The layout that fact, trash, and flag files are written in. Whenever it changes, this is raised
+// and a migration from the previous version is added to the end of `MIGRATIONS`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Upgrades a file from the version matching its position in `MIGRATIONS` to the next one
+type Migration = fn(Value, FileKind) -> Value;
+
+const MIGRATIONS: &[Migration] = &[wrap_bare_array];
+
+// What a file holds, which decides the field its entries are kept under
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    Facts,
+    Trash,
+    Flags,
+}
+
+impl FileKind {
+    fn field(self) -> &'static str {
+        match self {
+            FileKind::Facts => "facts",
+            FileKind::Trash => "trash",
+            FileKind::Flags => "flags",
+        }
+    }
+}
+
+// A file's entries along with the version of its layout, ex. `{"version": 1, "facts": [...]}`
+#[derive(Serialize)]
+pub struct Envelope<'a, T: Serialize> {
+    version: u32,
+    #[serde(flatten)]
+    entries: BTreeMap<&'static str, &'a [T]>,
+}
+
+impl<'a, T: Serialize> Envelope<'a, T> {
+    pub fn new(kind: FileKind, entries: &'a [T]) -> Self {
+        Envelope {
+            version: SCHEMA_VERSION,
+            entries: BTreeMap::from([(kind.field(), entries)]),
+        }
+    }
+}
+
+// Version 0 files were a bare array of entries
+fn wrap_bare_array(value: Value, kind: FileKind) -> Value {
+    let mut envelope = Map::new();
+    envelope.insert("version".to_string(), Value::from(1));
+    envelope.insert(kind.field().to_string(), value);
+    Value::Object(envelope)
+}
+
+fn version_of(value: &Value) -> Result<u32, StorageError> {
+    match value {
+        Value::Array(_) => Ok(0),
+        Value::Object(fields) => fields
+            .get("version")
+            .and_then(Value::as_u64)
+            .map(|version| version as u32)
+            .ok_or_else(|| serde_json::Error::custom("the file has no `version`").into()),
+        _ => Err(serde_json::Error::custom("the file isn't an array or an object").into()),
+    }
+}
+
+// Reads a file in any known layout, upgrading it to the current one. Along with the entries,
+// the version the file was written in is returned, so callers can tell if it was migrated.
+pub fn read<T: DeserializeOwned>(
+    contents: &str,
+    kind: FileKind,
+) -> Result<(Vec<T>, u32), StorageError> {
+    let mut value: Value = serde_json::from_str(contents)?;
+
+    let version = version_of(&value)?;
+    if version > SCHEMA_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        value = migration(value, kind);
+    }
+
+    let entries = match value {
+        Value::Object(mut fields) => fields.remove(kind.field()).unwrap_or_default(),
+        _ => Value::Null,
+    };
+    // A file with nothing in it is the same as an empty list
+    if entries.is_null() {
+        return Ok((Vec::new(), version));
+    }
+
+    Ok((serde_json::from_value(entries)?, version))
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len(), SCHEMA_VERSION as usize);
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        let written = serde_json::to_string(&Envelope::new(FileKind::Trash, &[1u64, 2])).unwrap();
+        assert_eq!(written, r#"{"version":1,"trash":[1,2]}"#);
+
+        let (entries, version): (Vec<u64>, u32) = read(&written, FileKind::Trash).unwrap();
+        assert_eq!((entries, version), (vec![1, 2], SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn bare_arrays_are_migrated() {
+        let (entries, version): (Vec<u64>, u32) = read("[3, 4]", FileKind::Facts).unwrap();
+        assert_eq!((entries, version), (vec![3, 4], 0));
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let result = read::<u64>(r#"{"version": 99, "facts": []}"#, FileKind::Facts);
+        assert!(matches!(result, Err(StorageError::UnsupportedVersion(99))));
+    }
+}
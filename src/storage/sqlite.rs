@@ -0,0 +1,282 @@
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{FactChange, FlagChange, JsonStorage, Storage, StorageError};
+use crate::animal_facts::{Fact, TrashedFact};
+use crate::{AnimalType, Config, FactFlag};
+
+// Each fact, trashed fact, and flag is kept whole as JSON so new fields don't need schema changes.
+// Rows are returned in insertion order, which keeps lists in the same order as the JSON backend.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS facts (
+        position INTEGER PRIMARY KEY,
+        animal TEXT NOT NULL,
+        id INTEGER NOT NULL,
+        fact TEXT NOT NULL,
+        UNIQUE (animal, id)
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        position INTEGER PRIMARY KEY,
+        animal TEXT NOT NULL,
+        id INTEGER NOT NULL,
+        fact TEXT NOT NULL,
+        UNIQUE (animal, id)
+    );
+    CREATE TABLE IF NOT EXISTS flags (
+        position INTEGER PRIMARY KEY,
+        id INTEGER NOT NULL UNIQUE,
+        flag TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS migrations (
+        name TEXT PRIMARY KEY
+    );
+";
+
+// SQLite integers are signed, so IDs are stored with the same bits as an `i64`
+fn sql_id(id: u64) -> i64 {
+    id as i64
+}
+
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path, config: &Config) -> Result<Self, StorageError> {
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        import_json(&mut conn, config)?;
+
+        Ok(SqliteStorage {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn load_rows<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        sql: &str,
+        param: Option<&str>,
+    ) -> Result<Vec<T>, StorageError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = match param {
+            Some(param) => stmt
+                .query_map(params![param], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<String>, _>>()?,
+            None => stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<String>, _>>()?,
+        };
+
+        rows.iter()
+            .map(|row| serde_json::from_str(row).map_err(StorageError::from))
+            .collect()
+    }
+}
+
+// Brings over the JSON files in `facts_dir` the first time each animal, and the flags, are seen.
+// Afterwards the JSON files are left alone and the database is the only copy that changes.
+fn import_json(conn: &mut Connection, config: &Config) -> Result<(), StorageError> {
+    let json = JsonStorage::new(&config.facts_dir);
+    let tx = conn.transaction()?;
+
+    for animal in &config.animal_fact_types {
+        let migration = format!("import:{}", animal.slug);
+        if has_migrated(&tx, &migration)? {
+            continue;
+        }
+
+        if let Some(facts) = json.load_facts(animal)? {
+            for fact in &facts {
+                insert_fact(&tx, animal, fact)?;
+            }
+            for trashed in json.load_trash(animal)? {
+                insert_trashed(&tx, animal, &trashed)?;
+            }
+            info!("Imported {} {} facts into SQLite", facts.len(), animal.name);
+        }
+        tx.execute("INSERT INTO migrations (name) VALUES (?1)", [&migration])?;
+    }
+
+    if !has_migrated(&tx, "import:flags")? {
+        for flag in json.load_flags()?.unwrap_or_default() {
+            insert_flag(&tx, &flag)?;
+        }
+        tx.execute("INSERT INTO migrations (name) VALUES ('import:flags')", [])?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn has_migrated(tx: &Transaction, name: &str) -> Result<bool, StorageError> {
+    Ok(tx
+        .query_row("SELECT 1 FROM migrations WHERE name = ?1", [name], |_| {
+            Ok(())
+        })
+        .optional()?
+        .is_some())
+}
+
+fn insert_fact(tx: &Transaction, animal: &AnimalType, fact: &Fact) -> Result<(), StorageError> {
+    tx.execute(
+        "INSERT INTO facts (animal, id, fact) VALUES (?1, ?2, ?3)",
+        params![
+            animal.slug.as_str(),
+            sql_id(fact.id),
+            serde_json::to_string(fact)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_trashed(
+    tx: &Transaction,
+    animal: &AnimalType,
+    trashed: &TrashedFact,
+) -> Result<(), StorageError> {
+    tx.execute(
+        "INSERT INTO trash (animal, id, fact) VALUES (?1, ?2, ?3)",
+        params![
+            animal.slug.as_str(),
+            sql_id(trashed.fact.id),
+            serde_json::to_string(trashed)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_flag(tx: &Transaction, flag: &FactFlag) -> Result<(), StorageError> {
+    tx.execute(
+        "INSERT INTO flags (id, flag) VALUES (?1, ?2)",
+        params![sql_id(flag.id), serde_json::to_string(flag)?],
+    )?;
+    Ok(())
+}
+
+fn delete_row(
+    tx: &Transaction,
+    table: &str,
+    animal: &AnimalType,
+    id: u64,
+) -> Result<(), StorageError> {
+    tx.execute(
+        &format!("DELETE FROM {} WHERE animal = ?1 AND id = ?2", table),
+        params![animal.slug.as_str(), sql_id(id)],
+    )?;
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn load_facts(&self, animal: &AnimalType) -> Result<Option<Vec<Fact>>, StorageError> {
+        let facts: Vec<Fact> = self.load_rows(
+            "SELECT fact FROM facts WHERE animal = ?1 ORDER BY position",
+            Some(animal.slug.as_str()),
+        )?;
+
+        // Matches a missing file with the JSON backend, since there's nothing to serve
+        if facts.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(facts))
+        }
+    }
+
+    fn load_trash(&self, animal: &AnimalType) -> Result<Vec<TrashedFact>, StorageError> {
+        self.load_rows(
+            "SELECT fact FROM trash WHERE animal = ?1 ORDER BY position",
+            Some(animal.slug.as_str()),
+        )
+    }
+
+    fn load_flags(&self) -> Result<Option<Vec<FactFlag>>, StorageError> {
+        self.load_rows("SELECT flag FROM flags ORDER BY position", None)
+            .map(Some)
+    }
+
+    // Only the change is applied, so the full lists are never rewritten
+    fn save_facts(
+        &self,
+        animal: &AnimalType,
+        _facts: &[Fact],
+        _trash: &[TrashedFact],
+        change: &FactChange,
+    ) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        match change {
+            FactChange::Added(fact) => insert_fact(&tx, animal, fact)?,
+            FactChange::Imported(facts) => {
+                for fact in facts {
+                    insert_fact(&tx, animal, fact)?;
+                }
+            }
+            FactChange::Updated(fact) => {
+                tx.execute(
+                    "UPDATE facts SET fact = ?1 WHERE animal = ?2 AND id = ?3",
+                    params![
+                        serde_json::to_string(fact)?,
+                        animal.slug.as_str(),
+                        sql_id(fact.id)
+                    ],
+                )?;
+            }
+            FactChange::Trashed(trashed) => {
+                delete_row(&tx, "facts", animal, trashed.fact.id)?;
+                insert_trashed(&tx, animal, trashed)?;
+            }
+            FactChange::Restored(fact) => {
+                delete_row(&tx, "trash", animal, fact.id)?;
+                insert_fact(&tx, animal, fact)?;
+            }
+            FactChange::TrashEmptied => {
+                tx.execute(
+                    "DELETE FROM trash WHERE animal = ?1",
+                    [animal.slug.as_str()],
+                )?;
+            }
+            FactChange::Replaced { facts, trash } => {
+                for table in ["facts", "trash"] {
+                    tx.execute(
+                        &format!("DELETE FROM {} WHERE animal = ?1", table),
+                        [animal.slug.as_str()],
+                    )?;
+                }
+                for fact in facts {
+                    insert_fact(&tx, animal, fact)?;
+                }
+                for trashed in trash {
+                    insert_trashed(&tx, animal, trashed)?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn save_flags(&self, _flags: &[FactFlag], change: &FlagChange) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        match change {
+            FlagChange::Added(flag) => insert_flag(&tx, flag)?,
+            FlagChange::Removed(id) => {
+                tx.execute("DELETE FROM flags WHERE id = ?1", [sql_id(*id)])?;
+            }
+            FlagChange::Replaced(flags) => {
+                tx.execute("DELETE FROM flags", [])?;
+                for flag in flags {
+                    insert_flag(&tx, flag)?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -35,7 +35,7 @@ async fn view_flags() {
     let (state, state2) = (gen_state(&dir), gen_state(&dir));
 
     let raw = state.fact_flags.unwrap();
-    let expected = raw.read().unwrap();
+    let expected = raw.read().unwrap().clone();
 
     let req_json = AdminFlagRequest {
         key: gen_admin_all_perms().key,
@@ -60,7 +60,7 @@ async fn view_flags() {
 
     let returned: Vec<FactFlag> = test::call_and_read_body_json(&app, req).await;
 
-    assert_eq!(returned, *expected);
+    assert_eq!(returned, expected);
 }
 
 #[actix_rt::test]
@@ -88,7 +88,7 @@ async fn add_flag_no_factid() {
         fact_id: None,
         flag_id: None,
         reason: None,
-        fact_type: Some(Animal::Cat),
+        fact_type: Some(Animal::from("cat")),
     };
 
     assert_eq!(
@@ -105,7 +105,7 @@ async fn add_flag_bad_factid() {
         fact_id: Some(18446744073709551615),
         flag_id: None,
         reason: None,
-        fact_type: Some(Animal::Cat),
+        fact_type: Some(Animal::from("cat")),
     };
 
     assert_eq!(
@@ -124,12 +124,12 @@ async fn add_flag_ok() {
         fact_id: Some(6682463169732688062),
         flag_id: None,
         reason: None,
-        fact_type: Some(Animal::Cat),
+        fact_type: Some(Animal::from("cat")),
     };
 
     let resp = test_admin_flag_req(req_json, "/admin/flag/add", state).await;
 
-    let expected = JsonResp::new(201, CreatedAction::Flag.as_str());
+    let expected = animal_api::JsonResp::with_message(201, CreatedAction::Flag.message());
     assert_eq!(resp, expected)
 }
 
@@ -196,5 +196,9 @@ async fn delete_flag_ok() {
 
     let resp = test::call_and_read_body(&app, req).await;
 
-    assert_eq!(resp, Bytes::from_static(b""))
+    assert_eq!(resp, Bytes::from_static(b""));
+
+    // The removal should have been saved too
+    let reloaded = gen_state(&dir).fact_flags.unwrap();
+    assert!(reloaded.read().unwrap().is_empty());
 }
@@ -13,10 +13,8 @@ async fn no_admins_loaded() {
     state.config.admins = Vec::new();
 
     let req_json = AdminFactRequest {
-        fact_id: None,
         fact_content: Some("SpookyFact".to_string()),
-        animal_type: Animal::Cat,
-        key: gen_admin_all_perms().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key)
     };
 
     assert_eq!(
@@ -29,10 +27,8 @@ async fn no_admins_loaded() {
 async fn invalid_key() {
     let dir = make_dir();
     let req_json = AdminFactRequest {
-        fact_id: None,
         fact_content: Some("SpookyFact".to_string()),
-        animal_type: Animal::Cat,
-        key: "BadKey".to_string(),
+        ..gen_fact_request(Animal::from("cat"), "BadKey".to_string())
     };
 
     assert_eq!(
@@ -45,10 +41,8 @@ async fn invalid_key() {
 async fn missing_permission_add() {
     let dir = make_dir();
     let req_json = AdminFactRequest {
-        fact_id: None,
         fact_content: Some("SpookyFact".to_string()),
-        animal_type: Animal::Cat,
-        key: gen_admin_delete_only().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_delete_only().key)
     };
 
     assert_eq!(
@@ -62,9 +56,7 @@ async fn missing_permission_delete() {
     let dir = make_dir();
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_add_only().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_add_only().key)
     };
 
     assert_eq!(
@@ -78,9 +70,7 @@ async fn missing_permission_view() {
     let dir = make_dir();
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_view_only().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_view_only().key)
     };
 
     assert_eq!(
@@ -94,9 +84,7 @@ async fn missing_permission_all() {
     let dir = make_dir();
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_no_perms().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_no_perms().key)
     };
 
     assert_eq!(
@@ -109,14 +97,12 @@ async fn missing_permission_all() {
 async fn modify_cat_unloaded() {
     let dir = make_dir();
     let mut state = gen_state(&dir);
-    state.fact_lists.cat_facts = None;
+    state.fact_lists.remove(&Animal::from("cat"));
 
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Cat,
         // // Get the key of the 'add_only' admin
-        key: state.config.admins[0].key.clone(),
+        ..gen_fact_request(Animal::from("cat"), state.config.admins[0].key.clone())
     };
 
     assert_eq!(
@@ -129,14 +115,12 @@ async fn modify_cat_unloaded() {
 async fn modify_dog_unloaded() {
     let dir = make_dir();
     let mut state = gen_state(&dir);
-    state.fact_lists.dog_facts = None;
+    state.fact_lists.remove(&Animal::from("dog"));
 
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Dog,
         // // Get the key of the 'all_perms' admin
-        key: state.config.admins[4].key.clone(),
+        ..gen_fact_request(Animal::from("dog"), state.config.admins[4].key.clone())
     };
 
     assert_eq!(
@@ -145,20 +129,34 @@ async fn modify_dog_unloaded() {
     )
 }
 
+#[actix_rt::test]
+async fn modify_unknown_animal() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_content: Some("SpookyFact".to_string()),
+        ..gen_fact_request(Animal::from("fox"), gen_admin_all_perms().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/add", gen_state(&dir)).await,
+        RESP_UNKNOWN_ANIMAL
+    )
+}
+
 #[actix_rt::test]
 async fn list_facts() {
     let dir = make_dir();
     let uri = "/admin/fact/list";
     let state = gen_state(&dir);
-    let req_json = AdminFactRequest {
-        fact_id: None,
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_all_perms().key,
-    };
+    let req_json = gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key);
 
-    let raw = gen_state(&dir).fact_lists.cat_facts.unwrap();
-    let expected = raw.read().unwrap();
+    let raw = gen_state(&dir).fact_lists;
+    let expected = raw
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .read()
+        .unwrap()
+        .clone();
 
     let mock_state = Data::new(state);
     let app = test::init_service(
@@ -175,18 +173,13 @@ async fn list_facts() {
 
     let received: Vec<Fact> = test::call_and_read_body_json(&app, req).await;
 
-    assert_eq!(received, *expected)
+    assert_eq!(received, expected)
 }
 
 #[actix_rt::test]
 async fn add_fact_no_content() {
     let dir = make_dir();
-    let req_json = AdminFactRequest {
-        fact_id: None,
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_all_perms().key,
-    };
+    let req_json = gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key);
 
     assert_eq!(
         test_admin_fact_req(req_json, "/admin/fact/add", gen_state(&dir)).await,
@@ -200,30 +193,21 @@ async fn add_fact_ok() {
     let state = gen_state(&dir);
 
     let req_json = AdminFactRequest {
-        fact_id: None,
         fact_content: Some("Huzaaah, a new fact!".to_string()),
-        animal_type: Animal::Dog,
-        key: gen_admin_all_perms().key,
+        ..gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key)
     };
 
     let resp = test_admin_fact_req(req_json, "/admin/fact/add", state).await;
 
-    let word = CreatedAction::Fact {
-        animal: Animal::Dog,
-    };
-    let expected = JsonResp::new(201, word.as_str());
+    let word = CreatedAction::Fact { animal: "Dog" };
+    let expected = animal_api::JsonResp::with_message(201, word.message());
     assert_eq!(resp, expected);
 }
 
 #[actix_rt::test]
 async fn delete_fact_no_id() {
     let dir = make_dir();
-    let req_json = AdminFactRequest {
-        fact_id: None,
-        fact_content: None,
-        animal_type: Animal::Dog,
-        key: gen_admin_all_perms().key,
-    };
+    let req_json = gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key);
 
     assert_eq!(
         test_admin_fact_req(req_json, "/admin/fact/delete", gen_state(&dir)).await,
@@ -237,9 +221,7 @@ async fn delete_fact_bad_id() {
     let req_json = AdminFactRequest {
         // Example of a number that is not *currently* existing
         fact_id: Some(3),
-        fact_content: None,
-        animal_type: Animal::Dog,
-        key: gen_admin_all_perms().key,
+        ..gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key)
     };
 
     assert_eq!(
@@ -254,9 +236,7 @@ async fn delete_fact_ok() {
     let uri = "/admin/fact/delete";
     let req_json = AdminFactRequest {
         fact_id: Some(6682463169732688062),
-        fact_content: None,
-        animal_type: Animal::Cat,
-        key: gen_admin_all_perms().key,
+        ..gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key)
     };
 
     let state = gen_state(&dir);
@@ -278,3 +258,368 @@ async fn delete_fact_ok() {
 
     assert_eq!(resp, Bytes::from_static(b""))
 }
+
+#[actix_rt::test]
+async fn missing_permission_update() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: Some("Cats can't taste sweetness.".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_add_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/update", gen_state(&dir)).await,
+        RESP_MISSING_PERMS
+    )
+}
+
+#[actix_rt::test]
+async fn update_fact_no_content() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/update", gen_state(&dir)).await,
+        RESP_NO_CONTENT_SPECIFIED
+    )
+}
+
+#[actix_rt::test]
+async fn update_fact_bad_id() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(3),
+        fact_content: Some("Cats can't taste sweetness.".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/update", gen_state(&dir)).await,
+        RESP_ID_NOT_FOUND
+    )
+}
+
+#[actix_rt::test]
+async fn update_fact_ok() {
+    let dir = make_dir();
+    let uri = "/admin/fact/update";
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: Some("Cats can't taste sweetness.".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource(uri).route(web::post().to(admin::modify_fact))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri(uri)
+        .set_json(&req_json)
+        .to_request();
+
+    let resp = test::call_and_read_body(&app, req).await;
+    assert_eq!(resp, Bytes::from_static(b""));
+
+    // The fact should keep its ID, and the change should have been saved to disk
+    let reloaded = gen_state(&dir).fact_lists;
+    let facts = reloaded.get(&Animal::from("cat")).unwrap().read().unwrap();
+    let updated = facts
+        .iter()
+        .find(|fact| fact.id == 6682463169732688062)
+        .unwrap();
+    assert_eq!(updated.content, "Cats can't taste sweetness.");
+    assert!(updated.updated_at.is_some());
+}
+
+#[actix_rt::test]
+async fn clear_fact_source() {
+    let dir = make_dir();
+    let set_source = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_source: Some("https://example.com/wrong-citation".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+    let clear_source = AdminFactRequest {
+        fact_source: Some(String::new()),
+        ..set_source.clone()
+    };
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app =
+        test::init_service(App::new().app_data(mock_state.clone()).service(
+            web::resource("/admin/fact/update").route(web::post().to(admin::modify_fact)),
+        ))
+        .await;
+
+    let mut sources = Vec::new();
+    for req_json in [set_source, clear_source] {
+        let req = test::TestRequest::post()
+            .uri("/admin/fact/update")
+            .set_json(&req_json)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        let facts = mock_state
+            .fact_lists
+            .get(&Animal::from("cat"))
+            .unwrap()
+            .read()
+            .unwrap();
+        sources.push(facts[0].source.clone());
+    }
+
+    assert_eq!(
+        sources,
+        [Some("https://example.com/wrong-citation".to_string()), None]
+    );
+}
+
+#[actix_rt::test]
+async fn add_fact_metadata() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_content: Some("Dogs have about 1,700 taste buds.".to_string()),
+        fact_source: Some("https://example.com/dog-taste".to_string()),
+        ..gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key)
+    };
+
+    test_admin_fact_req(req_json, "/admin/fact/add", gen_state(&dir)).await;
+
+    let reloaded = gen_state(&dir).fact_lists;
+    let facts = reloaded.get(&Animal::from("dog")).unwrap().read().unwrap();
+    let added = facts.last().unwrap();
+
+    assert_eq!(added.created_by.as_deref(), Some("Tester"));
+    assert!(added.created_at.is_some());
+    assert_eq!(added.updated_at, None);
+    assert_eq!(
+        added.source.as_deref(),
+        Some("https://example.com/dog-taste")
+    );
+}
+
+#[actix_rt::test]
+async fn tag_fact_no_tags() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_tags: Some(vec![" ".to_string()]),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/tag/add", gen_state(&dir)).await,
+        RESP_NO_TAGS_SUPPLIED
+    )
+}
+
+#[actix_rt::test]
+async fn missing_permission_tag() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_tags: Some(vec!["taste".to_string()]),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_delete_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/tag/remove", gen_state(&dir)).await,
+        RESP_MISSING_PERMS
+    )
+}
+
+#[actix_rt::test]
+async fn tag_and_untag_fact() {
+    let dir = make_dir();
+    let tag_req = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_tags: Some(vec!["Anatomy".to_string(), "taste".to_string()]),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+    let untag_req = AdminFactRequest {
+        fact_tags: Some(vec!["TASTE".to_string()]),
+        ..tag_req.clone()
+    };
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(App::new().app_data(mock_state.clone()).service(
+        web::resource("/admin/fact/tag/{action}").route(web::post().to(admin::modify_fact)),
+    ))
+    .await;
+
+    for (uri, req_json) in [
+        ("/admin/fact/tag/add", tag_req),
+        ("/admin/fact/tag/remove", untag_req),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(&req_json)
+            .to_request();
+        let resp = test::call_and_read_body(&app, req).await;
+        assert_eq!(resp, Bytes::from_static(b""));
+    }
+
+    let reloaded = gen_state(&dir).fact_lists;
+    let facts = reloaded.get(&Animal::from("cat")).unwrap().read().unwrap();
+    let tagged = facts
+        .iter()
+        .find(|fact| fact.id == 6682463169732688062)
+        .unwrap();
+    assert_eq!(tagged.tags, vec!["anatomy".to_string()]);
+}
+
+#[actix_rt::test]
+async fn translation_no_language() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: Some("Katzen schmecken nichts Süßes.".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/translation/set", gen_state(&dir)).await,
+        RESP_NO_LANGUAGE_SUPPLIED
+    )
+}
+
+#[actix_rt::test]
+async fn translation_default_language() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: Some("Cats can't taste sweetness.".to_string()),
+        language: Some("EN".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/translation/set", gen_state(&dir)).await,
+        RESP_DEFAULT_LANGUAGE
+    )
+}
+
+#[actix_rt::test]
+async fn delete_missing_translation() {
+    let dir = make_dir();
+    let req_json = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        language: Some("de".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/translation/delete", gen_state(&dir)).await,
+        RESP_NO_TRANSLATION
+    )
+}
+
+#[actix_rt::test]
+async fn set_and_delete_translations() {
+    let dir = make_dir();
+    let set_de = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: Some("Katzen schmecken nichts Süßes.".to_string()),
+        language: Some("de".to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_edit_only().key)
+    };
+    let set_fr = AdminFactRequest {
+        fact_content: Some("Les chats ne sentent pas le sucré.".to_string()),
+        language: Some("FR".to_string()),
+        ..set_de.clone()
+    };
+    let delete_de = AdminFactRequest {
+        fact_content: None,
+        ..set_de.clone()
+    };
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(App::new().app_data(mock_state.clone()).service(
+        web::resource("/admin/fact/translation/{action}").route(web::post().to(admin::modify_fact)),
+    ))
+    .await;
+
+    for (uri, req_json) in [
+        ("/admin/fact/translation/set", set_de),
+        ("/admin/fact/translation/set", set_fr),
+        ("/admin/fact/translation/delete", delete_de),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(&req_json)
+            .to_request();
+        let resp = test::call_and_read_body(&app, req).await;
+        assert_eq!(resp, Bytes::from_static(b""));
+    }
+
+    let reloaded = gen_state(&dir).fact_lists;
+    let facts = reloaded.get(&Animal::from("cat")).unwrap().read().unwrap();
+    let translated = facts
+        .iter()
+        .find(|fact| fact.id == 6682463169732688062)
+        .unwrap();
+    assert_eq!(translated.translations.len(), 1);
+    assert_eq!(
+        translated.translations["fr"],
+        "Les chats ne sentent pas le sucré."
+    );
+}
+
+#[actix_rt::test]
+async fn search_follows_admin_changes() {
+    let dir = make_dir();
+    let add_req = AdminFactRequest {
+        fact_content: Some("Axolotl-shaped dog toys are popular.".to_string()),
+        ..gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key)
+    };
+    let delete_req = AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: None,
+        animal_type: Animal::from("cat"),
+        ..add_req.clone()
+    };
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(
+                web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(web::resource("/search").route(web::get().to(search::search_facts))),
+    )
+    .await;
+
+    for (uri, req_json) in [
+        ("/admin/fact/add", add_req),
+        ("/admin/fact/delete", delete_req),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(&req_json)
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/search?q=axolotl")
+        .to_request();
+    let found: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        found[0]["fact"]["content"],
+        "Axolotl-shaped dog toys are popular."
+    );
+
+    let req = test::TestRequest::get().uri("/search?q=sweet").to_request();
+    let removed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(removed, serde_json::json!([]));
+}
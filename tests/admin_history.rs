@@ -0,0 +1,111 @@
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::Revision;
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+fn gen_history_req(
+    fact_content: Option<&str>,
+    revision: Option<usize>,
+    key: String,
+) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_id: Some(6682463169732688062),
+        fact_content: fact_content.map(str::to_string),
+        revision,
+        ..gen_fact_request(Animal::from("cat"), key)
+    }
+}
+
+#[actix_rt::test]
+async fn missing_permission_revert() {
+    let dir = make_dir();
+    let req_json = gen_history_req(None, Some(0), gen_admin_view_only().key);
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/revert", gen_state(&dir)).await,
+        RESP_MISSING_PERMS
+    )
+}
+
+#[actix_rt::test]
+async fn revert_no_revision() {
+    let dir = make_dir();
+    let req_json = gen_history_req(None, None, gen_admin_edit_only().key);
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/revert", gen_state(&dir)).await,
+        RESP_NO_REVISION_SUPPLIED
+    )
+}
+
+#[actix_rt::test]
+async fn revert_bad_revision() {
+    let dir = make_dir();
+    // Facts without any recorded changes only have their original content
+    let req_json = gen_history_req(None, Some(1), gen_admin_edit_only().key);
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/fact/revert", gen_state(&dir)).await,
+        RESP_REVISION_NOT_FOUND
+    )
+}
+
+#[actix_rt::test]
+async fn update_and_revert() {
+    let dir = make_dir();
+    let key = gen_admin_all_perms().key;
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app =
+        test::init_service(App::new().app_data(mock_state.clone()).service(
+            web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+        ))
+        .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/update")
+        .set_json(gen_history_req(
+            Some("Cats can't taste sweetness."),
+            None,
+            key.clone(),
+        ))
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/history")
+        .set_json(gen_history_req(None, None, key.clone()))
+        .to_request();
+    let history: Vec<Revision> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].content, "Cats cannot taste anything sweet.");
+    assert_eq!(history[0].revised_by, None);
+    assert_eq!(history[1].content, "Cats can't taste sweetness.");
+    assert_eq!(history[1].revised_by.as_deref(), Some("Tester"));
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/revert")
+        .set_json(gen_history_req(None, Some(0), key.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    // The revert is saved as a new revision rather than dropping the update
+    let reloaded = gen_state(&dir).fact_lists;
+    let facts = reloaded.get(&Animal::from("cat")).unwrap().read().unwrap();
+    let reverted = facts
+        .iter()
+        .find(|fact| fact.id == 6682463169732688062)
+        .unwrap();
+    assert_eq!(reverted.content, "Cats cannot taste anything sweet.");
+    assert_eq!(reverted.history.len(), 3);
+    assert_eq!(reverted.history[2].content, reverted.content);
+}
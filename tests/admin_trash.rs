@@ -0,0 +1,146 @@
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::{Fact, TrashedFact};
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+fn gen_trash_req(fact_id: Option<u64>, key: String) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_id,
+        ..gen_fact_request(Animal::from("cat"), key)
+    }
+}
+
+#[actix_rt::test]
+async fn missing_permission_trash() {
+    let dir = make_dir();
+    let req_json = gen_trash_req(None, gen_admin_delete_only().key);
+
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/trash/list", gen_state(&dir)).await,
+        RESP_MISSING_PERMS
+    )
+}
+
+#[actix_rt::test]
+async fn restore_bad_id() {
+    let dir = make_dir();
+    let req_json = gen_trash_req(Some(6682463169732688062), gen_admin_all_perms().key);
+
+    // The fact exists, but it was never deleted
+    assert_eq!(
+        test_admin_fact_req(req_json, "/admin/trash/restore", gen_state(&dir)).await,
+        RESP_ID_NOT_FOUND
+    )
+}
+
+#[actix_rt::test]
+async fn delete_and_restore() {
+    let dir = make_dir();
+    let fact_id = 6682463169732688062;
+    let key = gen_admin_all_perms().key;
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(
+                web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/admin/trash/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/{animal}/fact/{id}")
+                    .route(web::get().to(animal_facts::get_fact_by_id)),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/delete")
+        .set_json(gen_trash_req(Some(fact_id), key.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    // Trashed facts are never served publicly
+    let req = test::TestRequest::get()
+        .uri("/cat/fact/6682463169732688062")
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_ID_NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri("/admin/trash/list")
+        .set_json(gen_trash_req(None, key.clone()))
+        .to_request();
+    let trash: Vec<TrashedFact> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].fact.id, fact_id);
+    assert_eq!(trash[0].deleted_by, "Tester");
+
+    let req = test::TestRequest::post()
+        .uri("/admin/trash/restore")
+        .set_json(gen_trash_req(Some(fact_id), key.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/cat/fact/6682463169732688062")
+        .to_request();
+    let restored: Fact = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(restored.id, fact_id);
+
+    // Both the list and the trash should have been saved
+    let reloaded = gen_state(&dir).fact_lists;
+    let cat = Animal::from("cat");
+    assert_eq!(reloaded.get(&cat).unwrap().read().unwrap().len(), 3);
+    assert!(reloaded.get_trash(&cat).unwrap().read().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn empty_trash() {
+    let dir = make_dir();
+    let key = gen_admin_all_perms().key;
+
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(
+                web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/admin/trash/{action}").route(web::post().to(admin::modify_fact)),
+            ),
+    )
+    .await;
+
+    for (uri, fact_id) in [
+        ("/admin/fact/delete", Some(6682463169732688062)),
+        ("/admin/fact/delete", Some(15766631097752163384)),
+        ("/admin/trash/empty", None),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(gen_trash_req(fact_id, key.clone()))
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&app, req).await,
+            Bytes::from_static(b"")
+        );
+    }
+
+    let reloaded = gen_state(&dir).fact_lists;
+    let cat = Animal::from("cat");
+    assert_eq!(reloaded.get(&cat).unwrap().read().unwrap().len(), 1);
+    assert!(reloaded.get_trash(&cat).unwrap().read().unwrap().is_empty());
+}
@@ -0,0 +1,270 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::bundle::{self, Bundle, RestoreMode, RestoreReport, BUNDLE_VERSION};
+use animal_api::storage::{self, StorageBackend};
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+async fn export(state: &Data<APIState>, key: String) -> actix_web::dev::ServiceResponse {
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .service(web::resource("/admin/export").route(web::post().to(admin::export))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/export")
+        .set_json(AdminExportRequest { key })
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+async fn restore(
+    state: &Data<APIState>,
+    bundle: Bundle,
+    mode: RestoreMode,
+) -> actix_web::dev::ServiceResponse {
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .service(web::resource("/admin/restore").route(web::post().to(admin::restore_bundle))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/restore")
+        .set_json(AdminRestoreRequest {
+            mode,
+            bundle,
+            key: gen_admin_all_perms().key,
+        })
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+fn cat_ids(state: &APIState) -> Vec<u64> {
+    state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .read()
+        .unwrap()
+        .iter()
+        .map(|fact| fact.id)
+        .collect()
+}
+
+#[actix_rt::test]
+async fn missing_permission_export() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let resp: generator::JsonResp =
+        test::read_body_json(export(&mock_state, gen_admin_edit_only().key).await).await;
+    assert_eq!(resp, RESP_MISSING_PERMS);
+}
+
+#[actix_rt::test]
+async fn export_everything() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let bundle: Bundle =
+        test::read_body_json(export(&mock_state, gen_admin_all_perms().key).await).await;
+    assert_eq!(bundle.version, BUNDLE_VERSION);
+    assert_eq!(bundle.counts.facts, 6);
+    assert_eq!(bundle.counts.trash, 0);
+    assert_eq!(bundle.counts.flags, 1);
+    assert_eq!(bundle.animals[&Animal::from("cat")].facts.len(), 3);
+    assert_eq!(bundle.flags.unwrap()[0].id, 6682463169732628062);
+}
+
+#[actix_rt::test]
+async fn restore_replace() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let bundle = bundle::export_state(&mock_state).unwrap();
+    let original = cat_ids(&mock_state);
+
+    // Only the cat facts are in the bundle, so the dog facts should be left alone
+    let mut cat_only = bundle.clone();
+    cat_only.animals.remove(&Animal::from("dog"));
+    cat_only
+        .animals
+        .get_mut(&Animal::from("cat"))
+        .unwrap()
+        .facts
+        .truncate(1);
+    cat_only.flags = Some(Vec::new());
+
+    let report: RestoreReport =
+        test::read_body_json(restore(&mock_state, cat_only, RestoreMode::Replace).await).await;
+    assert_eq!(
+        report,
+        RestoreReport {
+            facts: 1,
+            trash: 0,
+            flags: 0,
+            skipped: 0,
+        }
+    );
+    assert_eq!(cat_ids(&mock_state), original[..1]);
+    assert!(mock_state.fact_exists(&Animal::from("dog"), 12603598811629753927));
+    assert!(mock_state
+        .fact_flags
+        .as_ref()
+        .unwrap()
+        .read()
+        .unwrap()
+        .is_empty());
+    assert!(mock_state
+        .search_index
+        .read()
+        .unwrap()
+        .search("kittens", None)
+        .is_empty());
+
+    // Restoring the full bundle brings everything back
+    let report: RestoreReport =
+        test::read_body_json(restore(&mock_state, bundle, RestoreMode::Replace).await).await;
+    assert_eq!((report.facts, report.flags), (6, 1));
+    assert_eq!(cat_ids(&mock_state), original);
+    assert_eq!(
+        mock_state
+            .search_index
+            .read()
+            .unwrap()
+            .search("kittens", None)
+            .len(),
+        1
+    );
+
+    let stored = mock_state
+        .storage
+        .load_facts(&gen_animal_type("cat", "Cat"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.len(), 3);
+}
+
+#[actix_rt::test]
+async fn restore_merge() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let mut bundle = bundle::export_state(&mock_state).unwrap();
+
+    let cat = bundle.animals.get_mut(&Animal::from("cat")).unwrap();
+    let mut new_fact = cat.facts[0].clone();
+    new_fact.id = 1;
+    new_fact.content = "Cats can jump up to six times their length.".to_string();
+    cat.facts.push(new_fact);
+
+    let report: RestoreReport =
+        test::read_body_json(restore(&mock_state, bundle, RestoreMode::Merge).await).await;
+    assert_eq!(
+        report,
+        RestoreReport {
+            facts: 1,
+            trash: 0,
+            flags: 0,
+            skipped: 7,
+        }
+    );
+    assert_eq!(cat_ids(&mock_state).len(), 4);
+    assert!(mock_state.fact_exists(&Animal::from("cat"), 1));
+}
+
+#[actix_rt::test]
+async fn restore_invalid_bundle() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let bundle = bundle::export_state(&mock_state).unwrap();
+
+    let mut newer = bundle.clone();
+    newer.version = BUNDLE_VERSION + 1;
+    let resp: generator::JsonResp =
+        test::read_body_json(restore(&mock_state, newer, RestoreMode::Replace).await).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(
+            400,
+            format!(
+                "The bundle is version {}, but only up to version {} is supported",
+                BUNDLE_VERSION + 1,
+                BUNDLE_VERSION
+            )
+            .into()
+        )
+    );
+
+    let mut unknown = bundle;
+    let birds = unknown.animals[&Animal::from("cat")].clone();
+    unknown.animals.insert(Animal::from("bird"), birds);
+    let resp: generator::JsonResp =
+        test::read_body_json(restore(&mock_state, unknown, RestoreMode::Replace).await).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(
+            400,
+            "The bundle can't be restored: `bird` isn't a configured animal".into()
+        )
+    );
+}
+
+#[actix_rt::test]
+async fn failed_restore_rolls_back() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.storage.backend = StorageBackend::Json;
+    let mock_state = Data::new(gen_state_from(config));
+
+    let mut bundle = bundle::export_state(&mock_state).unwrap();
+    for lists in bundle.animals.values_mut() {
+        lists.facts.truncate(1);
+    }
+
+    // The cat facts save fine, but the dog facts can't be written
+    let path = dir.path().join("dog_facts.json");
+    std::fs::remove_file(&path).unwrap();
+    std::fs::create_dir(&path).unwrap();
+
+    let resp: generator::JsonResp =
+        test::read_body_json(restore(&mock_state, bundle, RestoreMode::Replace).await).await;
+    assert_eq!(resp, RESP_SAVE_FAILED);
+
+    assert_eq!(cat_ids(&mock_state).len(), 3);
+    let stored = mock_state
+        .storage
+        .load_facts(&gen_animal_type("cat", "Cat"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.len(), 3);
+}
+
+#[actix_rt::test]
+async fn bundle_between_storages() {
+    let from = make_dir();
+    let config = gen_config(&from);
+    let bundle = {
+        let storage = storage::open(&config).unwrap();
+        bundle::export_storage(storage.as_ref(), &config).unwrap()
+    };
+
+    // A fresh instance without any facts yet
+    let to = tempdir::TempDir::new("facts").unwrap();
+    let to_config = gen_config(&to);
+    {
+        let storage = storage::open(&to_config).unwrap();
+        let report =
+            bundle::restore_storage(storage.as_ref(), &to_config, &bundle, RestoreMode::Merge)
+                .unwrap();
+        assert_eq!((report.facts, report.flags, report.skipped), (6, 1, 0));
+    }
+
+    let state = gen_state_from(to_config);
+    assert_eq!(cat_ids(&state).len(), 3);
+    assert_eq!(state.fact_flags.unwrap().read().unwrap().len(), 1);
+}
@@ -0,0 +1,78 @@
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+// Everything a config needs besides the animals, which go before it since it ends with a table
+const REST_OF_CONFIG: &str = r#"
+logging_dir = "./logs"
+logging_level = "info"
+facts_dir = "./facts"
+flagging_enabled = false
+flaggers = []
+admins = []
+
+[server]
+ip = "127.0.0.1"
+port = 8080
+"#;
+
+#[test]
+fn animal_tables() {
+    let config = format!(
+        r#"animal_fact_types = [
+            {{ slug = "cat", name = "Cat", file = "cat_facts.json" }},
+            {{ slug = "axolotl", name = "Axolotl", file = "axolotls.json" }},
+        ]
+        {}"#,
+        REST_OF_CONFIG
+    );
+
+    let config: Config = toml::from_str(&config).unwrap();
+    assert_eq!(config.animal_fact_types[0], gen_animal_type("cat", "Cat"));
+    assert_eq!(config.animal_fact_types[1].file, "axolotls.json");
+}
+
+#[test]
+fn animal_names_still_load() {
+    // How animals were listed before they could be configured
+    let config = format!("animal_fact_types = [\"Cat\", \"Dog\"]\n{}", REST_OF_CONFIG);
+
+    let config: Config = toml::from_str(&config).unwrap();
+    assert_eq!(
+        config.animal_fact_types,
+        [gen_animal_type("cat", "Cat"), gen_animal_type("dog", "Dog")]
+    );
+}
+
+#[test]
+fn default_language_normalized() {
+    let config = format!(
+        "default_language = \"en_US\"\nanimal_fact_types = [\"Cat\"]\n{}",
+        REST_OF_CONFIG
+    );
+
+    let config: Config = toml::from_str(&config).unwrap();
+    assert_eq!(config.default_language, "en-us");
+}
+
+#[test]
+fn flagged_weight_range() {
+    let parse = |weight: &str| {
+        let config = format!(
+            "flagged_weight = {}\nanimal_fact_types = [\"Cat\"]\n{}",
+            weight, REST_OF_CONFIG
+        );
+        toml::from_str::<Config>(&config)
+    };
+
+    assert_eq!(parse("0.5").unwrap().flagged_weight, 0.5);
+    assert_eq!(parse("0").unwrap().flagged_weight, 0.0);
+    for weight in ["nan", "inf", "-0.5", "1.5"] {
+        let err = parse(weight).unwrap_err().to_string();
+        assert!(
+            err.contains("flagged_weight must be from 0 to 1"),
+            "{}",
+            err
+        );
+    }
+}
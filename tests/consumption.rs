@@ -1,3 +1,4 @@
+use animal_api::animal_facts::Fact;
 use animal_api::*;
 mod generator;
 use crate::generator::*;
@@ -7,13 +8,335 @@ use crate::generator::*;
 async fn get_fact_cat() {
     // This will fail if for some reason its not returning a fact
     let dir = make_dir();
-    test_fact_consumer_req(Animal::Cat, "/cat/fact", gen_state(&dir)).await
+    test_fact_consumer_req("/cat/fact", gen_state(&dir)).await
 }
 
 #[actix_rt::test]
 async fn get_fact_dog() {
     let dir = make_dir();
-    test_fact_consumer_req(Animal::Dog, "/dog/fact", gen_state(&dir)).await
+    test_fact_consumer_req("/dog/fact", gen_state(&dir)).await
+}
+
+#[actix_rt::test]
+async fn get_fact_configured_species() {
+    let dir = make_dir();
+    std::fs::write(
+        dir.path().join("axolotl_facts.json"),
+        r#"[{"id": 1, "content": "Axolotls can regrow their limbs."}]"#,
+    )
+    .unwrap();
+
+    let mut config = gen_config(&dir);
+    config
+        .animal_fact_types
+        .push(gen_animal_type("axolotl", "Axolotl"));
+
+    test_fact_consumer_req("/axolotl/fact", gen_state_from(config)).await
+}
+
+#[actix_rt::test]
+async fn get_fact_with_source() {
+    let dir = make_dir();
+    std::fs::write(
+        dir.path().join("cat_facts.json"),
+        r#"[{"id": 1, "content": "Cats sleep a lot.", "created_by": "Alice", "source": "https://example.com/cats"}]"#,
+    )
+    .unwrap();
+
+    let resp: serde_json::Value = test_public_req("/cat/fact", gen_state(&dir)).await;
+
+    assert_eq!(resp["source"], "https://example.com/cats");
+    // Admin metadata isn't public
+    assert!(resp.get("created_by").is_none());
+}
+
+#[actix_rt::test]
+async fn get_fact_by_tag() {
+    let dir = make_dir();
+    std::fs::write(
+        dir.path().join("cat_facts.json"),
+        r#"[
+            {"id": 1, "content": "Cats have 230 bones.", "tags": ["anatomy"]},
+            {"id": 2, "content": "Cats were worshipped in Egypt.", "tags": ["history"]},
+            {"id": 3, "content": "Cats sleep a lot."}
+        ]"#,
+    )
+    .unwrap();
+
+    // Tags are case insensitive
+    let resp: Fact = test_public_req("/cat/fact?tag=Anatomy", gen_state(&dir)).await;
+    assert_eq!(resp.id, 1);
+    assert_eq!(resp.tags, vec!["anatomy".to_string()]);
+}
+
+#[actix_rt::test]
+async fn get_fact_by_tag_no_match() {
+    let dir = make_dir();
+    let resp: generator::JsonResp = test_public_req("/dog/fact?tag=history", gen_state(&dir)).await;
+
+    assert_eq!(resp, RESP_NO_MATCHING_FACTS)
+}
+
+fn write_translated_facts(dir: &tempdir::TempDir) {
+    std::fs::write(
+        dir.path().join("cat_facts.json"),
+        r#"[{
+            "id": 1,
+            "content": "Cats cannot taste anything sweet.",
+            "translations": {"de": "Katzen schmecken nichts Süßes.", "pt": "Gatos não sentem sabor doce."}
+        }]"#,
+    )
+    .unwrap();
+}
+
+#[actix_rt::test]
+async fn get_fact_lang_query() {
+    let dir = make_dir();
+    write_translated_facts(&dir);
+
+    // The query parameter wins over the header
+    let req = actix_web::test::TestRequest::get()
+        .uri("/cat/fact?lang=pt-BR")
+        .insert_header(("Accept-Language", "de"));
+    let resp: serde_json::Value = test_public_call(req, gen_state(&dir)).await;
+
+    assert_eq!(resp["lang"], "pt");
+    assert_eq!(resp["content"], "Gatos não sentem sabor doce.");
+}
+
+#[actix_rt::test]
+async fn get_fact_accept_language() {
+    let dir = make_dir();
+    write_translated_facts(&dir);
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/cat/fact")
+        .insert_header(("Accept-Language", "fr;q=0.9, de;q=0.8"));
+    let resp: serde_json::Value = test_public_call(req, gen_state(&dir)).await;
+
+    assert_eq!(resp["lang"], "de");
+    assert_eq!(resp["content"], "Katzen schmecken nichts Süßes.");
+}
+
+#[actix_rt::test]
+async fn get_fact_language_fallback() {
+    let dir = make_dir();
+    write_translated_facts(&dir);
+
+    let resp: serde_json::Value = test_public_req("/cat/fact?lang=ja", gen_state(&dir)).await;
+
+    assert_eq!(resp["lang"], "en");
+    assert_eq!(resp["content"], "Cats cannot taste anything sweet.");
+}
+
+#[actix_rt::test]
+async fn get_fact_by_id() {
+    let dir = make_dir();
+    let resp: Fact = test_public_req("/cat/fact/15766631097752163384", gen_state(&dir)).await;
+
+    assert_eq!(resp.id, 15766631097752163384);
+    assert_eq!(
+        resp.content,
+        "Kittens remain with their mother till the age of 9 weeks."
+    );
+}
+
+#[actix_rt::test]
+async fn get_fact_by_id_translated() {
+    let dir = make_dir();
+    write_translated_facts(&dir);
+
+    let resp: serde_json::Value = test_public_req("/cat/fact/1?lang=de", gen_state(&dir)).await;
+
+    assert_eq!(resp["content"], "Katzen schmecken nichts Süßes.");
+}
+
+#[actix_rt::test]
+async fn get_fact_by_bad_id() {
+    let dir = make_dir();
+
+    // The fact exists, just not for this animal
+    let resp: generator::JsonResp =
+        test_public_req("/dog/fact/15766631097752163384", gen_state(&dir)).await;
+    assert_eq!(resp, RESP_ID_NOT_FOUND);
+
+    let resp: generator::JsonResp =
+        test_public_req("/dog/fact/not-a-number", gen_state(&dir)).await;
+    assert_eq!(resp, RESP_ID_NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn get_facts_batch() {
+    let dir = make_dir();
+    let state = gen_state(&dir);
+    let req_counter = state.req_counter.clone();
+
+    // The test config caps batches at 2 facts
+    let resp: Vec<Fact> = test_public_req("/cat/facts?count=10", state).await;
+
+    assert_eq!(resp.len(), 2);
+    assert_ne!(resp[0].id, resp[1].id);
+    assert_eq!(req_counter.with_label_values(&["Cat"]).get(), 1);
+}
+
+#[actix_rt::test]
+async fn get_facts_batch_filtered() {
+    let dir = make_dir();
+    std::fs::write(
+        dir.path().join("cat_facts.json"),
+        r#"[
+            {"id": 1, "content": "Cats have 230 bones.", "tags": ["anatomy"]},
+            {"id": 2, "content": "Cats were worshipped in Egypt.", "tags": ["history"]}
+        ]"#,
+    )
+    .unwrap();
+
+    // Only one fact matches, so only one can be returned
+    let resp: Vec<Fact> = test_public_req("/cat/facts?count=2&tag=history", gen_state(&dir)).await;
+
+    assert_eq!(resp.len(), 1);
+    assert_eq!(resp[0].id, 2);
+}
+
+#[actix_rt::test]
+async fn get_facts_batch_zero() {
+    let dir = make_dir();
+    let resp: generator::JsonResp = test_public_req("/dog/facts?count=0", gen_state(&dir)).await;
+
+    assert_eq!(resp, RESP_BAD_COUNT)
+}
+
+#[actix_rt::test]
+async fn get_fact_seeded() {
+    let dir = make_dir();
+    let first: serde_json::Value = test_public_req("/cat/fact?seed=42", gen_state(&dir)).await;
+    assert_eq!(first["seed"], 42);
+
+    // Every pick with the same seed is the same
+    for _ in 0..5 {
+        let resp: serde_json::Value = test_public_req("/cat/fact?seed=42", gen_state(&dir)).await;
+        assert_eq!(resp, first);
+    }
+
+    // Unseeded picks are given a seed that repeats them
+    let resp: serde_json::Value = test_public_req("/cat/fact", gen_state(&dir)).await;
+    let uri = format!("/cat/fact?seed={}", resp["seed"].as_u64().unwrap());
+    let repeated: serde_json::Value = test_public_req(&uri, gen_state(&dir)).await;
+    assert_eq!(repeated, resp);
+}
+
+#[actix_rt::test]
+async fn get_facts_batch_seeded() {
+    let dir = make_dir();
+    let first: Vec<serde_json::Value> =
+        test_public_req("/dog/facts?count=2&seed=7", gen_state(&dir)).await;
+    assert_eq!(first.len(), 2);
+    assert!(first.iter().all(|fact| fact["seed"] == 7));
+
+    for _ in 0..5 {
+        let resp: Vec<serde_json::Value> =
+            test_public_req("/dog/facts?count=2&seed=7", gen_state(&dir)).await;
+        assert_eq!(resp, first);
+    }
+}
+
+#[actix_rt::test]
+async fn get_any_fact() {
+    let dir = make_dir();
+    let resp: serde_json::Value = test_public_req("/fact", gen_state(&dir)).await;
+    let fact: Fact = serde_json::from_value(resp.clone()).unwrap();
+
+    // The fact is from the animal the response says
+    let animal = resp["animal"].as_str().unwrap();
+    let by_id: Fact =
+        test_public_req(&format!("/{}/fact/{}", animal, fact.id), gen_state(&dir)).await;
+    assert_eq!(by_id.content, fact.content);
+}
+
+#[actix_rt::test]
+async fn get_any_fact_listed_animals() {
+    let dir = make_dir();
+    for weighting in ["fact", "species"] {
+        let uri = format!("/fact?animals=Dog,%20dog&weighting={}", weighting);
+        let resp: serde_json::Value = test_public_req(&uri, gen_state(&dir)).await;
+        assert_eq!(resp["animal"], "dog");
+    }
+
+    let resp: generator::JsonResp = test_public_req("/fact?animals=cat,fox", gen_state(&dir)).await;
+    assert_eq!(resp, RESP_UNKNOWN_ANIMAL);
+}
+
+#[actix_rt::test]
+async fn get_any_fact_seeded() {
+    let dir = make_dir();
+    let first: serde_json::Value =
+        test_public_req("/fact?weighting=species&seed=3", gen_state(&dir)).await;
+    assert_eq!(first["seed"], 3);
+
+    for _ in 0..5 {
+        let resp: serde_json::Value =
+            test_public_req("/fact?weighting=species&seed=3", gen_state(&dir)).await;
+        assert_eq!(resp, first);
+    }
+}
+
+#[actix_rt::test]
+async fn search_facts() {
+    let dir = make_dir();
+    let resp: serde_json::Value = test_public_req("/search?q=Cat%20sweets", gen_state(&dir)).await;
+    let results = resp.as_array().unwrap();
+
+    // "Cats cannot taste anything sweet." matches both words, so it ranks above the other cat fact
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["animal"], "cat");
+    assert_eq!(results[0]["fact"]["id"], 6682463169732688062u64);
+}
+
+#[actix_rt::test]
+async fn search_facts_single_animal() {
+    let dir = make_dir();
+    let resp: serde_json::Value =
+        test_public_req("/search?q=dogs%20cats&animal=dog&limit=1", gen_state(&dir)).await;
+    let results = resp.as_array().unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["animal"], "dog");
+}
+
+#[actix_rt::test]
+async fn search_skips_missing_facts() {
+    let dir = make_dir();
+    let state = gen_state(&dir);
+    // The best match is gone from the list but still in the index
+    state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .write()
+        .unwrap()
+        .retain(|fact| fact.id != 6682463169732688062);
+
+    let resp: serde_json::Value = test_public_req("/search?q=Cat%20sweets&limit=1", state).await;
+    let results = resp.as_array().unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_ne!(results[0]["fact"]["id"], 6682463169732688062u64);
+}
+
+#[actix_rt::test]
+async fn search_facts_no_query() {
+    let dir = make_dir();
+    let resp: generator::JsonResp = test_public_req("/search?q=%20!", gen_state(&dir)).await;
+
+    assert_eq!(resp, RESP_NO_QUERY)
+}
+
+#[actix_rt::test]
+async fn get_unknown_species() {
+    let dir = make_dir();
+    let resp: generator::JsonResp = test_public_req("/fox/fact", gen_state(&dir)).await;
+
+    assert_eq!(resp, RESP_UNKNOWN_ANIMAL)
 }
 
 #[actix_rt::test]
@@ -21,10 +344,10 @@ async fn get_fact_dog() {
 async fn get_unloaded_cat() {
     let dir = make_dir();
     let mut state = gen_state(&dir);
-    state.fact_lists.cat_facts = None;
+    state.fact_lists.remove(&Animal::from("cat"));
 
     // This will fail because the JSON returned is a JsonResp, not a Fact
-    test_fact_consumer_req(Animal::Cat, "/cat/fact", state).await
+    test_fact_consumer_req("/cat/fact", state).await
 }
 
 #[actix_rt::test]
@@ -32,9 +355,9 @@ async fn get_unloaded_cat() {
 async fn get_unloaded_dog() {
     let dir = make_dir();
     let mut state = gen_state(&dir);
-    state.fact_lists.dog_facts = None;
+    state.fact_lists.remove(&Animal::from("dog"));
 
-    test_fact_consumer_req(Animal::Dog, "/dog/fact", state).await
+    test_fact_consumer_req("/dog/fact", state).await
 }
 
 // Flag tests
@@ -45,7 +368,7 @@ async fn set_flag_unloaded() {
     state.config.flagging_enabled = false;
 
     let req_json = FactFlagRequest {
-        fact_type: Animal::Cat,
+        fact_type: Animal::from("cat"),
         fact_id: 6682463169732688062,
         reason: None,
         key: gen_flagger().key,
@@ -62,7 +385,7 @@ async fn set_flag_unloaded() {
 async fn set_flag_invalid_auth() {
     let dir = make_dir();
     let req_json = FactFlagRequest {
-        fact_type: Animal::Cat,
+        fact_type: Animal::from("cat"),
         fact_id: 6682463169732688062,
         reason: None,
         key: "AGreatPassword".to_string(),
@@ -79,7 +402,7 @@ async fn set_flag_invalid_auth() {
 async fn set_flag_bad_factid() {
     let dir = make_dir();
     let req_json = FactFlagRequest {
-        fact_type: Animal::Cat,
+        fact_type: Animal::from("cat"),
         fact_id: 18446744073709551615,
         reason: Some("A Reason".to_string()),
         key: gen_flagger().key,
@@ -92,12 +415,48 @@ async fn set_flag_bad_factid() {
     )
 }
 
+#[actix_rt::test]
+async fn set_flag_other_animals_fact() {
+    let dir = make_dir();
+    // The ID belongs to a cat fact, so there's no dog fact to flag
+    let req_json = FactFlagRequest {
+        fact_type: Animal::from("dog"),
+        fact_id: 6682463169732688062,
+        reason: Some("A Reason".to_string()),
+        key: gen_flagger().key,
+        flagger: None,
+    };
+
+    assert_eq!(
+        test_flag_consumer_req(req_json, "/flag", gen_state(&dir)).await,
+        RESP_ID_NOT_FOUND
+    )
+}
+
+#[actix_rt::test]
+async fn set_flag_first_flag() {
+    let dir = make_dir();
+    // This fact hasn't been flagged before
+    let req_json = FactFlagRequest {
+        fact_type: Animal::from("cat"),
+        fact_id: 15889153292243741260,
+        reason: Some("A Reason".to_string()),
+        key: gen_flagger().key,
+        flagger: None,
+    };
+
+    let resp = test_flag_consumer_req(req_json, "/flag", gen_state(&dir)).await;
+
+    let expected = animal_api::JsonResp::with_message(201, CreatedAction::Flag.message());
+    assert_eq!(resp, expected)
+}
+
 #[actix_rt::test]
 async fn set_flag_valid() {
     let dir = make_dir();
     let state = gen_state(&dir);
     let req_json = FactFlagRequest {
-        fact_type: Animal::Cat,
+        fact_type: Animal::from("cat"),
         fact_id: 6682463169732688062,
         reason: Some("A Reason".to_string()),
         key: gen_flagger().key,
@@ -106,6 +465,6 @@ async fn set_flag_valid() {
 
     let resp = test_flag_consumer_req(req_json, "/flag", state).await;
 
-    let expected = JsonResp::new(201, CreatedAction::Flag.as_str());
+    let expected = animal_api::JsonResp::with_message(201, CreatedAction::Flag.message());
     assert_eq!(resp, expected)
 }
@@ -0,0 +1,135 @@
+use actix_web::http::header;
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+use chrono::Utc;
+
+use animal_api::animal_facts::Fact;
+use animal_api::*;
+use serde::Deserialize;
+mod generator;
+use crate::generator::*;
+
+#[derive(Deserialize)]
+struct DailyResp {
+    id: u64,
+    date: String,
+}
+
+async fn get_daily(state: &Data<APIState>, uri: &str) -> actix_web::dev::ServiceResponse {
+    let app = test::init_service(App::new().app_data(state.clone()).service(
+        web::resource("/{animal}/fact/daily").route(web::get().to(daily::get_daily_fact)),
+    ))
+    .await;
+
+    let req = test::TestRequest::get().uri(uri).to_request();
+    test::call_service(&app, req).await
+}
+
+#[actix_rt::test]
+async fn same_fact_all_day() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let first: DailyResp =
+        test::read_body_json(get_daily(&mock_state, "/cat/fact/daily").await).await;
+    assert_eq!(first.date, Utc::now().date_naive().to_string());
+
+    // Facts added during the day aren't counted until the next one
+    {
+        let mut facts = mock_state
+            .fact_lists
+            .get(&Animal::from("cat"))
+            .unwrap()
+            .write()
+            .unwrap();
+        for id in 1..=20 {
+            facts.push(Fact {
+                created_at: Some(Utc::now()),
+                ..Fact::new(id, &format!("Cat fact {}", id))
+            });
+        }
+    }
+
+    let second: DailyResp =
+        test::read_body_json(get_daily(&mock_state, "/cat/fact/daily").await).await;
+    assert_eq!(first.id, second.id);
+}
+
+#[actix_rt::test]
+async fn flags_and_weights_keep_the_pick() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.flagged_weight = 0.5;
+    let mock_state = Data::new(gen_state_from(config));
+
+    let first: DailyResp =
+        test::read_body_json(get_daily(&mock_state, "/cat/fact/daily").await).await;
+
+    mock_state
+        .fact_flags
+        .as_ref()
+        .unwrap()
+        .write()
+        .unwrap()
+        .push(FactFlag {
+            id: 1,
+            fact_type: Animal::from("cat"),
+            fact_id: first.id,
+            reason: None,
+            flagger: "Flagger".to_string(),
+        });
+    for fact in mock_state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .write()
+        .unwrap()
+        .iter_mut()
+        .filter(|fact| fact.id != first.id)
+    {
+        fact.weight = Some(100.0);
+    }
+
+    let second: DailyResp =
+        test::read_body_json(get_daily(&mock_state, "/cat/fact/daily").await).await;
+    assert_eq!(first.id, second.id);
+}
+
+#[actix_rt::test]
+async fn cached_until_rollover() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let resp = get_daily(&mock_state, "/dog/fact/daily?tz=Asia/Tokyo").await;
+    assert_eq!(resp.status(), 200);
+
+    let cache_control = resp
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let max_age: i64 = cache_control
+        .strip_prefix("public, max-age=")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((0..=24 * 60 * 60).contains(&max_age));
+    assert!(resp
+        .headers()
+        .get(header::EXPIRES)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .ends_with(":00:00 GMT"));
+}
+
+#[actix_rt::test]
+async fn unknown_timezone() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let resp: generator::JsonResp =
+        test::read_body_json(get_daily(&mock_state, "/cat/fact/daily?tz=Mars/Olympus").await).await;
+    assert_eq!(resp, RESP_BAD_TIMEZONE);
+}
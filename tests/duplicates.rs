@@ -0,0 +1,194 @@
+use actix_web::http::StatusCode;
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::Fact;
+use animal_api::duplicates::{DuplicateAction, SimilarResp};
+use animal_api::import::{ImportFormat, ImportReport, SimilarEntry};
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+fn gen_add_request(animal: &str, content: &str, key: String) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_content: Some(content.to_string()),
+        ..gen_fact_request(Animal::from(animal), key)
+    }
+}
+
+async fn send<T: serde::Serialize>(
+    state: &Data<APIState>,
+    uri: &str,
+    request: T,
+) -> actix_web::dev::ServiceResponse {
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .service(web::resource("/admin/fact/add").route(web::post().to(admin::modify_fact)))
+            .service(
+                web::resource("/admin/fact/duplicates").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/admin/fact/import").route(web::post().to(admin::import_facts)),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri(uri)
+        .set_json(request)
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+fn fact_count(state: &APIState, animal: &str) -> usize {
+    state
+        .fact_lists
+        .get(&Animal::from(animal))
+        .unwrap()
+        .read()
+        .unwrap()
+        .len()
+}
+
+#[actix_rt::test]
+async fn similar_fact_warns() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let request = gen_add_request("cat", "Cats cannot taste sweet.", gen_admin_all_perms().key);
+    let resp = send(&mock_state, "/admin/fact/add", request).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let resp: SimilarResp = test::read_body_json(resp).await;
+    assert_eq!(
+        resp,
+        SimilarResp {
+            code: 201,
+            message: "Cat fact added".into(),
+            similar_ids: vec![6682463169732688062],
+        }
+    );
+    assert_eq!(fact_count(&mock_state, "cat"), 4);
+}
+
+#[actix_rt::test]
+async fn similar_fact_rejected() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.duplicates.action = DuplicateAction::Reject;
+    let mock_state = Data::new(gen_state_from(config));
+
+    let request = gen_add_request(
+        "cat",
+        "  cats cannot TASTE anything sweet. ",
+        gen_admin_all_perms().key,
+    );
+    let resp = send(&mock_state, "/admin/fact/add", request).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let resp: SimilarResp = test::read_body_json(resp).await;
+    assert_eq!(resp.message, RESP_TOO_SIMILAR.message);
+    assert_eq!(resp.similar_ids, [6682463169732688062]);
+    assert_eq!(fact_count(&mock_state, "cat"), 3);
+
+    // Anything different enough is still added
+    let request = gen_add_request("cat", "Cats have whiskers.", gen_admin_all_perms().key);
+    let resp: generator::JsonResp =
+        test::read_body_json(send(&mock_state, "/admin/fact/add", request).await).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(201, "Cat fact added".into())
+    );
+}
+
+#[actix_rt::test]
+async fn similar_entries_imported() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.duplicates.action = DuplicateAction::Reject;
+    let mock_state = Data::new(gen_state_from(config));
+
+    let request = AdminImportRequest {
+        animal_type: Animal::from("cat"),
+        format: ImportFormat::Text,
+        content_field: None,
+        data: "Cats cannot taste sweet.\nCats have whiskers.\nCats have whiskers!".to_string(),
+        key: gen_admin_all_perms().key,
+    };
+    let report: ImportReport =
+        test::read_body_json(send(&mock_state, "/admin/fact/import", request).await).await;
+    assert_eq!((report.added, report.skipped, report.rejected), (1, 0, 2));
+    assert_eq!(
+        report.rejections,
+        [
+            "Entry 1 is too similar to an existing fact",
+            "Entry 3 is too similar to an existing fact"
+        ]
+    );
+
+    // The last entry is similar to the one just before it
+    let added = mock_state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .read()
+        .unwrap()[3]
+        .id;
+    assert_eq!(
+        report.similar,
+        [
+            SimilarEntry {
+                entry: 1,
+                similar_ids: vec![6682463169732688062],
+            },
+            SimilarEntry {
+                entry: 3,
+                similar_ids: vec![added],
+            },
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn duplicate_report() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let request = gen_add_request("dog", "Dogs have no perms.", gen_admin_no_perms().key);
+    let resp: generator::JsonResp =
+        test::read_body_json(send(&mock_state, "/admin/fact/duplicates", request).await).await;
+    assert_eq!(resp, RESP_MISSING_PERMS);
+
+    let clusters: Vec<Vec<Fact>> = test::read_body_json(
+        send(
+            &mock_state,
+            "/admin/fact/duplicates",
+            gen_add_request("dog", "", gen_admin_edit_only().key),
+        )
+        .await,
+    )
+    .await;
+    assert!(clusters.is_empty());
+
+    let request = gen_add_request(
+        "dog",
+        "Dogs have sweat glands between their paws.",
+        gen_admin_all_perms().key,
+    );
+    send(&mock_state, "/admin/fact/add", request).await;
+    assert_eq!(fact_count(&mock_state, "dog"), 4);
+
+    let clusters: Vec<Vec<Fact>> = test::read_body_json(
+        send(
+            &mock_state,
+            "/admin/fact/duplicates",
+            gen_add_request("dog", "", gen_admin_edit_only().key),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(clusters.len(), 1);
+    assert_eq!(clusters[0].len(), 2);
+    assert_eq!(clusters[0][0].id, 8861147842456047965);
+}
@@ -3,8 +3,9 @@
 use actix_web::{test, web, web::Data, App};
 use animal_api::*;
 use animal_facts::*;
-use prometheus::{IntCounter, IntCounterVec, Opts, Registry};
-use serde::{Deserialize, Serialize};
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use storage::{StorageBackend, StorageConfig};
 use tempdir::TempDir;
 
 // This structure and the below comparision exists because we can't deserialize
@@ -29,6 +30,10 @@ pub fn gen_admin_add_only() -> Admin {
             view_facts: true,
             add_fact: true,
             delete_fact: false,
+            edit_fact: false,
+            manage_trash: false,
+            reload: false,
+            backup: false,
             view_flags: true,
             add_flag: true,
             delete_flag: false,
@@ -44,6 +49,10 @@ pub fn gen_admin_delete_only() -> Admin {
             view_facts: true,
             add_fact: false,
             delete_fact: true,
+            edit_fact: false,
+            manage_trash: false,
+            reload: false,
+            backup: false,
             view_flags: true,
             add_flag: false,
             delete_flag: true,
@@ -59,6 +68,10 @@ pub fn gen_admin_view_only() -> Admin {
             view_facts: true,
             add_fact: false,
             delete_fact: false,
+            edit_fact: false,
+            manage_trash: false,
+            reload: false,
+            backup: false,
             view_flags: false,
             add_flag: false,
             delete_flag: false,
@@ -74,6 +87,29 @@ pub fn gen_admin_no_perms() -> Admin {
             view_facts: false,
             add_fact: false,
             delete_fact: false,
+            edit_fact: false,
+            manage_trash: false,
+            reload: false,
+            backup: false,
+            view_flags: false,
+            add_flag: false,
+            delete_flag: false,
+        },
+    }
+}
+
+pub fn gen_admin_edit_only() -> Admin {
+    Admin {
+        name: "Tester".to_string(),
+        key: "edit_only".to_string(),
+        permissions: Perms {
+            view_facts: true,
+            add_fact: false,
+            delete_fact: false,
+            edit_fact: true,
+            manage_trash: false,
+            reload: false,
+            backup: false,
             view_flags: false,
             add_flag: false,
             delete_flag: false,
@@ -89,6 +125,10 @@ pub fn gen_admin_all_perms() -> Admin {
             view_facts: true,
             add_fact: true,
             delete_fact: true,
+            edit_fact: true,
+            manage_trash: true,
+            reload: true,
+            backup: true,
             view_flags: true,
             add_flag: false,
             delete_flag: true,
@@ -103,12 +143,62 @@ pub fn gen_flagger() -> Flagger {
     }
 }
 
-pub fn gen_state(tmp_dir: &TempDir) -> APIState {
-    let config = Config {
+pub fn gen_animal_type(slug: &str, name: &str) -> AnimalType {
+    AnimalType {
+        slug: Animal::new(slug),
+        name: name.to_string(),
+        file: format!("{}_facts.json", slug),
+    }
+}
+
+// An admin fact request with only the animal and key, which the fields a test needs can be set on top of
+pub fn gen_fact_request(animal_type: Animal, key: String) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_id: None,
+        fact_content: None,
+        fact_source: None,
+        fact_tags: None,
+        fact_weight: None,
+        language: None,
+        revision: None,
+        animal_type,
+        key,
+    }
+}
+
+// Every test runs against the JSON files by default, but another backend can be picked with
+// `ANIMAL_API_TEST_STORAGE`, ex. `ANIMAL_API_TEST_STORAGE=sqlite cargo test`
+pub fn gen_storage_config() -> StorageConfig {
+    let backend = match std::env::var("ANIMAL_API_TEST_STORAGE").as_deref() {
+        Ok("sqlite") => StorageBackend::Sqlite,
+        Ok("journal") => StorageBackend::Journal,
+        _ => StorageBackend::Json,
+    };
+
+    StorageConfig {
+        backend,
+        database: None,
+        compact_interval: None,
+        rewrite_migrated: false,
+    }
+}
+
+pub fn gen_config(tmp_dir: &TempDir) -> Config {
+    Config {
         logging_dir: "./test_logs".to_string(),
         logging_level: "info".to_string(),
         facts_dir: tmp_dir.path().as_os_str().to_string_lossy().to_string(),
-        animal_fact_types: vec![Animal::Cat, Animal::Dog],
+        storage: gen_storage_config(),
+        default_language: "en".to_string(),
+        max_batch_count: 2,
+        watch_interval: 0,
+        id_scheme: ids::IdScheme::Random,
+        ids_as_strings: false,
+        duplicates: duplicates::DuplicateConfig::default(),
+        shuffle: shuffle::ShuffleConfig::default(),
+        daily_timezone: chrono_tz::UTC,
+        flagged_weight: 1.0,
+        animal_fact_types: vec![gen_animal_type("cat", "Cat"), gen_animal_type("dog", "Dog")],
         flagging_enabled: true,
         server: ServerConfig {
             ip: "127.0.0.1".parse().unwrap(),
@@ -120,17 +210,24 @@ pub fn gen_state(tmp_dir: &TempDir) -> APIState {
             gen_admin_delete_only(),
             gen_admin_no_perms(),
             gen_admin_all_perms(),
+            gen_admin_edit_only(),
         ],
         flaggers: vec![gen_flagger()],
-    };
+    }
+}
+
+pub fn gen_state(tmp_dir: &TempDir) -> APIState {
+    gen_state_from(gen_config(tmp_dir))
+}
 
-    let fact_count: IntCounterVec = IntCounterVec::new(
+pub fn gen_state_from(config: Config) -> APIState {
+    let fact_count: IntGaugeVec = IntGaugeVec::new(
         Opts::new("fact_count", "How many animal facts are currently loaded"),
         &["animal"],
     )
     .unwrap();
-    let flag_count: IntCounter =
-        IntCounter::new("flag_count", "How many facts have been flagged").unwrap();
+    let flag_count: IntGauge =
+        IntGauge::new("flag_count", "How many facts have been flagged").unwrap();
     let req_count: IntCounterVec = IntCounterVec::new(
         Opts::new("api_request_count", "How many requests we have served"),
         &["animal"],
@@ -141,12 +238,21 @@ pub fn gen_state(tmp_dir: &TempDir) -> APIState {
     reg.register(Box::new(fact_count.clone())).unwrap();
     reg.register(Box::new(req_count.clone())).unwrap();
 
+    let storage = storage::open(&config).unwrap();
+    let fact_lists = load_fact_lists(&fact_count, &config, storage.as_ref());
+    let search_index = search::SearchIndex::new(&fact_lists, &config);
+
     APIState {
-        fact_lists: load_fact_lists(&fact_count, &config),
-        fact_flags: load_fact_flags(&flag_count, &config),
+        fact_lists,
+        fact_flags: load_fact_flags(&flag_count, &config, storage.as_ref()),
+        search_index: std::sync::RwLock::new(search_index),
+        shuffle_bags: shuffle::ShuffleBags::new(&config.shuffle),
+        storage,
         config,
         stat_register: reg,
         req_counter: req_count,
+        fact_count,
+        flag_count,
     }
 }
 
@@ -165,18 +271,13 @@ pub fn make_dir() -> TempDir {
     dir
 }
 
-pub async fn test_fact_consumer_req(animal: Animal, uri: &str, state: APIState) {
+pub async fn test_fact_consumer_req(uri: &str, state: APIState) {
     let mock_state = Data::new(state);
 
-    let endpoint = match animal {
-        Animal::Cat => animal_facts::get_cat_fact,
-        Animal::Dog => animal_facts::get_dog_fact,
-    };
-
     let app = test::init_service(
         App::new()
             .app_data(mock_state.clone())
-            .service(web::resource(uri).route(web::get().to(endpoint))),
+            .service(web::resource("/{animal}/fact").route(web::get().to(animal_facts::get_fact))),
     )
     .await;
 
@@ -184,6 +285,23 @@ pub async fn test_fact_consumer_req(animal: Animal, uri: &str, state: APIState)
     let _: Fact = test::call_and_read_body_json(&app, req).await;
 }
 
+pub async fn test_public_req<T: DeserializeOwned>(uri: &str, state: APIState) -> T {
+    test_public_call(test::TestRequest::get().uri(uri), state).await
+}
+
+pub async fn test_public_call<T: DeserializeOwned>(req: test::TestRequest, state: APIState) -> T {
+    let mock_state = Data::new(state);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .configure(routes::configure),
+    )
+    .await;
+
+    test::call_and_read_body_json(&app, req.to_request()).await
+}
+
 pub async fn test_flag_consumer_req(req: FactFlagRequest, uri: &str, state: APIState) -> JsonResp {
     let mock_state = Data::new(state);
 
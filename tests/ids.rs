@@ -0,0 +1,84 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::ids::IdScheme;
+use animal_api::*;
+use serde_json::{json, Value};
+mod generator;
+use crate::generator::*;
+
+fn gen_add_request(content: &str) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_content: Some(content.to_string()),
+        ..gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key)
+    }
+}
+
+#[actix_rt::test]
+async fn monotonic_ids() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.id_scheme = IdScheme::Monotonic;
+    let mock_state = Data::new(gen_state_from(config));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource("/admin/fact/add").route(web::post().to(admin::modify_fact))),
+    )
+    .await;
+
+    // The example facts all have random IDs too large to continue from, so counting starts over
+    for content in ["Cats purr.", "Cats nap."] {
+        let req = test::TestRequest::post()
+            .uri("/admin/fact/add")
+            .set_json(gen_add_request(content))
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let cat = Animal::from("cat");
+    assert!(mock_state.fact_exists(&cat, 1));
+    assert!(mock_state.fact_exists(&cat, 2));
+}
+
+#[actix_rt::test]
+async fn ids_as_strings() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.ids_as_strings = true;
+
+    let resp: Value =
+        test_public_req("/cat/fact/15766631097752163384", gen_state_from(config)).await;
+    assert_eq!(resp["id"], json!("15766631097752163384"));
+}
+
+#[actix_rt::test]
+async fn string_ids_accepted() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource("/flag").route(web::post().to(flagging::set_flag))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/flag")
+        .set_json(json!({
+            "fact_type": "cat",
+            "fact_id": "6682463169732688062",
+            "reason": null,
+            "key": gen_flagger().key,
+            "flagger": null,
+        }))
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(201, CreatedAction::Flag.message())
+    );
+
+    let flags = mock_state.fact_flags.as_ref().unwrap().read().unwrap();
+    assert_eq!(flags[1].fact_id, 6682463169732688062);
+}
@@ -0,0 +1,235 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::import::{self, ImportFormat, ImportReport};
+use animal_api::storage::{self, StorageBackend};
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+fn gen_import_request(
+    key: String,
+    animal: &str,
+    format: ImportFormat,
+    content_field: Option<&str>,
+    data: &str,
+) -> AdminImportRequest {
+    AdminImportRequest {
+        animal_type: Animal::from(animal),
+        format,
+        content_field: content_field.map(str::to_string),
+        data: data.to_string(),
+        key,
+    }
+}
+
+async fn send_import(
+    state: &Data<APIState>,
+    request: AdminImportRequest,
+) -> actix_web::dev::ServiceResponse {
+    let app =
+        test::init_service(App::new().app_data(state.clone()).service(
+            web::resource("/admin/fact/import").route(web::post().to(admin::import_facts)),
+        ))
+        .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/import")
+        .set_json(request)
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+#[actix_rt::test]
+async fn missing_permission_import() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let request = gen_import_request(
+        gen_admin_no_perms().key,
+        "cat",
+        ImportFormat::Text,
+        None,
+        "Cats purr.",
+    );
+    let resp: generator::JsonResp =
+        test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!(resp, RESP_MISSING_PERMS);
+}
+
+#[actix_rt::test]
+async fn import_json_with_field() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let data = r#"[
+        {"fact": "Cats purr at around 25 vibrations per second.", "source": "https://example.com"},
+        {"fact": "  cats CANNOT taste anything   sweet. "},
+        "A group of cats is called a clowder.",
+        {"content": "Wrong field"},
+        {"fact": ""},
+        42
+    ]"#;
+    let request = gen_import_request(
+        gen_admin_all_perms().key,
+        "cat",
+        ImportFormat::Json,
+        Some("fact"),
+        data,
+    );
+    let report: ImportReport = test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!(
+        report,
+        ImportReport {
+            added: 2,
+            skipped: 1,
+            rejected: 3,
+            rejections: vec![
+                "Entry 4 has no `fact` text".to_string(),
+                "Entry 5 has no content".to_string(),
+                "Entry 6 isn't text or an object".to_string(),
+            ],
+            similar: Vec::new(),
+        }
+    );
+
+    let cat = Animal::from("cat");
+    let facts = mock_state.fact_lists.get(&cat).unwrap().read().unwrap();
+    assert_eq!(facts.len(), 5);
+    assert_eq!(facts[3].source.as_deref(), Some("https://example.com"));
+    assert_eq!(facts[4].created_by.as_deref(), Some("Tester"));
+
+    // Imported facts are saved and searchable like any other
+    let saved = mock_state
+        .storage
+        .load_facts(&gen_animal_type("cat", "Cat"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.len(), 5);
+    let index = mock_state.search_index.read().unwrap();
+    assert_eq!(index.search("clowder", None).len(), 1);
+}
+
+#[actix_rt::test]
+async fn import_csv_and_text() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let key = gen_admin_all_perms().key;
+
+    let data = "source,content\n\
+        ,Dogs have three eyelids.\n\
+        https://example.com,\"Dogs sweat through their paws, mostly.\"\n\
+        ,Dogs have three eyelids.\n";
+    let request = gen_import_request(key.clone(), "dog", ImportFormat::Csv, None, data);
+    let report: ImportReport = test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!((report.added, report.skipped, report.rejected), (2, 1, 0));
+
+    let data = "Dogs dream.\n\nDogs have three eyelids.\nDogs dream.\n";
+    let request = gen_import_request(key, "dog", ImportFormat::Text, None, data);
+    let report: ImportReport = test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!((report.added, report.skipped, report.rejected), (1, 2, 0));
+
+    let dog = Animal::from("dog");
+    let facts = mock_state.fact_lists.get(&dog).unwrap().read().unwrap();
+    let contents: Vec<&str> = facts.iter().map(|fact| fact.content.as_str()).collect();
+    assert_eq!(
+        contents[3..],
+        [
+            "Dogs have three eyelids.",
+            "Dogs sweat through their paws, mostly.",
+            "Dogs dream."
+        ]
+    );
+    assert_eq!(facts[4].source.as_deref(), Some("https://example.com"));
+}
+
+#[actix_rt::test]
+async fn import_unreadable_data() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let key = gen_admin_all_perms().key;
+
+    let request = gen_import_request(key.clone(), "cat", ImportFormat::Json, None, "{}");
+    let resp: generator::JsonResp =
+        test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(400, "The JSON must be an array of facts".into())
+    );
+
+    let request = gen_import_request(key, "cat", ImportFormat::Csv, Some("fact"), "content\nA");
+    let resp: generator::JsonResp =
+        test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(400, "The CSV has no `fact` column".into())
+    );
+}
+
+#[actix_rt::test]
+async fn import_into_unloaded_animal() {
+    let dir = make_dir();
+    std::fs::remove_file(dir.path().join("dog_facts.json")).unwrap();
+    let mut config = gen_config(&dir);
+    config.storage.backend = StorageBackend::Json;
+    let mock_state = Data::new(gen_state_from(config));
+    let dog = Animal::from("dog");
+    assert!(mock_state.fact_lists.get(&dog).is_none());
+
+    let request = gen_import_request(
+        gen_admin_all_perms().key,
+        "dog",
+        ImportFormat::Text,
+        None,
+        "Dogs dream.",
+    );
+    let report: ImportReport = test::read_body_json(send_import(&mock_state, request).await).await;
+    assert_eq!(report.added, 1);
+
+    assert_eq!(
+        mock_state
+            .fact_lists
+            .get(&dog)
+            .unwrap()
+            .read()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(dir.path().join("dog_facts.json").exists());
+}
+
+#[actix_rt::test]
+async fn import_without_server() {
+    let dir = make_dir();
+    let config = gen_config(&dir);
+    let entries = import::parse(
+        "Cats cannot taste anything sweet.\nCats sleep for most of the day.",
+        ImportFormat::Text,
+        import::DEFAULT_CONTENT_FIELD,
+    )
+    .unwrap();
+
+    {
+        let storage = storage::open(&config).unwrap();
+        let report = import::import_into_storage(
+            storage.as_ref(),
+            &gen_animal_type("cat", "Cat"),
+            entries,
+            &config,
+        )
+        .unwrap();
+        assert_eq!((report.added, report.skipped), (1, 1));
+    }
+
+    let state = gen_state_from(config);
+    let facts = state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .read()
+        .unwrap();
+    assert_eq!(facts.len(), 4);
+    assert_eq!(facts[3].content, "Cats sleep for most of the day.");
+    assert_eq!(facts[3].created_by, None);
+}
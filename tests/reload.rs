@@ -0,0 +1,177 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::PublicFact;
+use animal_api::reload::ReloadSummary;
+use animal_api::storage::StorageBackend;
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+// These tests edit the files directly, which the other backends wouldn't see
+fn gen_json_state(dir: &tempdir::TempDir) -> APIState {
+    let mut config = gen_config(dir);
+    config.storage.backend = StorageBackend::Json;
+    gen_state_from(config)
+}
+
+const NEW_CAT_FACTS: &str = r#"[
+  {
+    "id": 1,
+    "content": "Cats have five toes on their front paws."
+  }
+]"#;
+
+#[actix_rt::test]
+async fn missing_permission_reload() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource("/admin/reload").route(web::post().to(admin::reload))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/reload")
+        .set_json(AdminReloadRequest {
+            key: gen_admin_edit_only().key,
+        })
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_MISSING_PERMS);
+}
+
+#[actix_rt::test]
+async fn reload_picks_up_changes() {
+    let dir = make_dir();
+    // Dog facts aren't loaded at first, but should be served once they show up
+    let dog_facts = std::fs::read_to_string(dir.path().join("dog_facts.json")).unwrap();
+    std::fs::remove_file(dir.path().join("dog_facts.json")).unwrap();
+
+    let mock_state = Data::new(gen_json_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource("/admin/reload").route(web::post().to(admin::reload)))
+            .service(web::resource("/{animal}/fact").route(web::get().to(animal_facts::get_fact)))
+            .service(
+                web::resource("/{animal}/fact/{id}")
+                    .route(web::get().to(animal_facts::get_fact_by_id)),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/dog/fact").to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_NOT_LOADED);
+
+    std::fs::write(dir.path().join("cat_facts.json"), NEW_CAT_FACTS).unwrap();
+    std::fs::write(dir.path().join("dog_facts.json"), dog_facts).unwrap();
+    std::fs::write(dir.path().join("fact_flags.json"), "[]").unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/admin/reload")
+        .set_json(AdminReloadRequest {
+            key: gen_admin_all_perms().key,
+        })
+        .to_request();
+    let summary: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        summary,
+        serde_json::json!({
+            "facts": { "cat": 1, "dog": 3 },
+            "flags": 0,
+            "changed": true,
+        })
+    );
+
+    // The metrics follow what's loaded
+    assert_eq!(mock_state.fact_count.with_label_values(&["Cat"]).get(), 1);
+    assert_eq!(mock_state.fact_count.with_label_values(&["Dog"]).get(), 3);
+    assert_eq!(mock_state.flag_count.get(), 0);
+
+    let req = test::TestRequest::get().uri("/cat/fact/1").to_request();
+    let fact: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(fact["content"], "Cats have five toes on their front paws.");
+
+    let req = test::TestRequest::get()
+        .uri("/cat/fact/6682463169732688062")
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_ID_NOT_FOUND);
+
+    let req = test::TestRequest::get().uri("/dog/fact").to_request();
+    let _: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    // The search index is rebuilt along with the lists
+    let index = mock_state.search_index.read().unwrap();
+    assert_eq!(index.search("toes", None).len(), 1);
+}
+
+#[actix_rt::test]
+async fn invalid_reload_keeps_old_data() {
+    let dir = make_dir();
+    let state = gen_json_state(&dir);
+
+    std::fs::write(dir.path().join("cat_facts.json"), "[{\"id\": 1, \"conte").unwrap();
+    assert!(reload::reload_facts(&state).is_err());
+
+    let duplicated = r#"[
+      { "id": 1, "content": "Cats sleep a lot." },
+      { "id": 1, "content": "Cats sleep even more." }
+    ]"#;
+    std::fs::write(dir.path().join("cat_facts.json"), duplicated).unwrap();
+    let errors = reload::reload_facts(&state).unwrap_err();
+    assert_eq!(errors, vec!["More than one Cat fact has the ID 1"]);
+
+    // Nothing was swapped in
+    let cat = Animal::from("cat");
+    let facts = state.fact_lists.get(&cat).unwrap().read().unwrap();
+    assert_eq!(facts.len(), 3);
+    let public = PublicFact::localized(&facts[0], &[], "en");
+    assert_eq!(public.id, 6682463169732688062);
+}
+
+#[actix_rt::test]
+async fn unchanged_reload() {
+    let dir = make_dir();
+    let state = gen_state(&dir);
+
+    let summary: ReloadSummary = reload::reload_facts(&state).unwrap();
+    assert!(!summary.changed);
+    assert_eq!(summary.facts[&Animal::from("cat")], 3);
+}
+
+#[actix_rt::test]
+async fn own_saves_arent_changes() {
+    let dir = make_dir();
+    let state = gen_json_state(&dir);
+    assert!(!reload::storage_differs(&state));
+
+    // A save made by the server matches what it's serving
+    let cat = gen_animal_type("cat", "Cat");
+    {
+        let mut facts = state.fact_lists.get(&cat.slug).unwrap().write().unwrap();
+        facts.pop();
+        let trash = state
+            .fact_lists
+            .get_trash(&cat.slug)
+            .unwrap()
+            .read()
+            .unwrap();
+        let change = storage::FactChange::Replaced {
+            facts: facts.clone(),
+            trash: trash.clone(),
+        };
+        state
+            .storage
+            .save_facts(&cat, &facts, &trash, &change)
+            .unwrap();
+    }
+    assert!(!reload::storage_differs(&state));
+
+    std::fs::write(dir.path().join("cat_facts.json"), NEW_CAT_FACTS).unwrap();
+    assert!(reload::storage_differs(&state));
+}
@@ -0,0 +1,41 @@
+use actix_web::web::Data;
+use actix_web::{test, App};
+
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+// The tests for each endpoint only register that endpoint, so these check them together
+#[actix_rt::test]
+async fn admin_routes_before_animal_routes() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .configure(routes::configure),
+    )
+    .await;
+
+    // `/admin/fact/add` has the same shape as `/{animal}/fact/{id}`, which is only for GET
+    let req_json = AdminFactRequest {
+        fact_content: Some("Huzaaah, a new fact!".to_string()),
+        ..gen_fact_request(Animal::from("dog"), gen_admin_all_perms().key)
+    };
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/add")
+        .set_json(&req_json)
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+
+    let word = CreatedAction::Fact { animal: "Dog" };
+    assert_eq!(
+        resp,
+        animal_api::JsonResp::with_message(201, word.message())
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/dog/fact/12603598811629753927")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
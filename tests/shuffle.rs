@@ -0,0 +1,94 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::{self, Fact};
+use animal_api::shuffle::ShuffleBags;
+use animal_api::*;
+use std::collections::HashSet;
+mod generator;
+use crate::generator::*;
+
+fn gen_shuffle_config(dir: &tempdir::TempDir) -> Config {
+    let mut config = gen_config(dir);
+    config.shuffle.enabled = true;
+    config.shuffle.file = Some(
+        dir.path()
+            .join("shuffle_bags.json")
+            .to_string_lossy()
+            .to_string(),
+    );
+    config
+}
+
+async fn get_facts(state: &Data<APIState>, reqs: Vec<test::TestRequest>) -> Vec<u64> {
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .service(web::resource("/{animal}/fact").route(web::get().to(animal_facts::get_fact)))
+            .service(
+                web::resource("/{animal}/facts").route(web::get().to(animal_facts::get_facts)),
+            ),
+    )
+    .await;
+
+    let mut ids = Vec::new();
+    for req in reqs {
+        let req = req.to_request();
+        if req.path().ends_with("facts") {
+            let facts: Vec<Fact> = test::call_and_read_body_json(&app, req).await;
+            ids.extend(facts.iter().map(|fact| fact.id));
+        } else {
+            let fact: Fact = test::call_and_read_body_json(&app, req).await;
+            ids.push(fact.id);
+        }
+    }
+    ids
+}
+
+#[actix_rt::test]
+async fn every_fact_before_repeats() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state_from(gen_shuffle_config(&dir)));
+
+    // The query parameter and header identify the same client
+    let reqs = vec![
+        test::TestRequest::get().uri("/cat/fact?client=guild"),
+        test::TestRequest::get()
+            .uri("/cat/facts?count=2")
+            .insert_header(("X-Client-Id", "guild")),
+    ];
+    let ids = get_facts(&mock_state, reqs).await;
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 3);
+
+    // Another client has its own bag
+    let reqs = (0..3)
+        .map(|_| test::TestRequest::get().uri("/cat/fact?client=other"))
+        .collect();
+    let ids = get_facts(&mock_state, reqs).await;
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 3);
+}
+
+#[actix_rt::test]
+async fn bags_are_saved() {
+    let dir = make_dir();
+    let config = gen_shuffle_config(&dir);
+    let mock_state = Data::new(gen_state_from(config.clone()));
+
+    let reqs = (0..2)
+        .map(|_| test::TestRequest::get().uri("/cat/fact?client=guild"))
+        .collect();
+    let seen = get_facts(&mock_state, reqs).await;
+    mock_state.shuffle_bags.save().unwrap();
+
+    // After a restart, the client only has the one fact it hasn't seen left
+    let restarted = ShuffleBags::new(&config.shuffle);
+    let facts = mock_state
+        .fact_lists
+        .get(&Animal::from("cat"))
+        .unwrap()
+        .read()
+        .unwrap();
+    let matching: Vec<&Fact> = facts.iter().collect();
+    let next = restarted.draw("guild", &Animal::from("cat"), None, &matching, 1);
+    assert!(!seen.contains(&next[0].id));
+}
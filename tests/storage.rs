@@ -0,0 +1,414 @@
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{test, App};
+
+use animal_api::storage::{self, JournalStorage, Storage, StorageBackend, StorageConfig};
+use animal_api::*;
+mod generator;
+use crate::generator::*;
+
+fn gen_sqlite_config(dir: &tempdir::TempDir) -> Config {
+    let mut config = gen_config(dir);
+    config.storage = StorageConfig {
+        backend: StorageBackend::Sqlite,
+        database: None,
+        compact_interval: None,
+        rewrite_migrated: false,
+    };
+    config
+}
+
+#[actix_rt::test]
+async fn sqlite_imports_json() {
+    let dir = make_dir();
+    let state = gen_state_from(gen_sqlite_config(&dir));
+
+    assert!(dir.path().join("facts.sqlite3").exists());
+    assert_eq!(
+        state
+            .fact_lists
+            .get(&Animal::from("cat"))
+            .unwrap()
+            .read()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(state.fact_flags.unwrap().read().unwrap().len(), 1);
+}
+
+#[actix_rt::test]
+async fn sqlite_only_imports_once() {
+    let dir = make_dir();
+    let key = gen_admin_all_perms().key;
+
+    let mock_state = Data::new(gen_state_from(gen_sqlite_config(&dir)));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(
+                web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/admin/flag/{action}").route(web::post().to(admin::modify_flag)),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/delete")
+        .set_json(AdminFactRequest {
+            fact_id: Some(6682463169732688062),
+            ..gen_fact_request(Animal::from("cat"), key.clone())
+        })
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/admin/flag/delete")
+        .set_json(AdminFlagRequest {
+            key,
+            fact_id: None,
+            flag_id: Some(6682463169732628062),
+            reason: None,
+            fact_type: None,
+        })
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    // The JSON files still have everything, but reopening the database shouldn't import them again
+    let reloaded = gen_state_from(gen_sqlite_config(&dir));
+    let cat = Animal::from("cat");
+    assert_eq!(
+        reloaded.fact_lists.get(&cat).unwrap().read().unwrap().len(),
+        2
+    );
+    assert_eq!(
+        reloaded.fact_lists.get_trash(&cat).unwrap().read().unwrap()[0]
+            .fact
+            .id,
+        6682463169732688062
+    );
+    assert!(reloaded.fact_flags.unwrap().read().unwrap().is_empty());
+
+    let mut json_config = gen_config(&dir);
+    json_config.storage.backend = StorageBackend::Json;
+    let json = gen_state_from(json_config);
+    assert_eq!(json.fact_lists.get(&cat).unwrap().read().unwrap().len(), 3);
+}
+
+fn gen_json_state(dir: &tempdir::TempDir) -> APIState {
+    let mut config = gen_config(dir);
+    config.storage.backend = StorageBackend::Json;
+    gen_state_from(config)
+}
+
+// A directory where the file should be makes every write to it fail
+fn block_writes(dir: &tempdir::TempDir, file: &str) {
+    let path = dir.path().join(file);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::create_dir(&path).unwrap();
+}
+
+#[actix_rt::test]
+async fn failed_fact_write_rolls_back() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_json_state(&dir));
+    block_writes(&dir, "cat_facts.json");
+
+    let app =
+        test::init_service(App::new().app_data(mock_state.clone()).service(
+            web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+        ))
+        .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/delete")
+        .set_json(AdminFactRequest {
+            fact_id: Some(6682463169732688062),
+            ..gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key)
+        })
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_SAVE_FAILED);
+
+    // Nothing should have moved, in memory or on disk
+    let cat = Animal::from("cat");
+    let facts = mock_state.fact_lists.get(&cat).unwrap().read().unwrap();
+    assert_eq!(facts.len(), 3);
+    assert_eq!(facts[0].id, 6682463169732688062);
+    assert!(mock_state
+        .fact_lists
+        .get_trash(&cat)
+        .unwrap()
+        .read()
+        .unwrap()
+        .is_empty());
+    assert!(!dir.path().join("cat_facts.trash.json").exists());
+    assert!(!dir.path().join("cat_facts.trash.json.tmp").exists());
+}
+
+#[actix_rt::test]
+async fn failed_flag_write_rolls_back() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_json_state(&dir));
+    block_writes(&dir, "fact_flags.json");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(web::resource("/flag").route(web::post().to(flagging::set_flag))),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/flag")
+        .set_json(FactFlagRequest {
+            fact_type: Animal::from("cat"),
+            fact_id: 6682463169732688062,
+            reason: None,
+            key: gen_flagger().key,
+            flagger: None,
+        })
+        .to_request();
+    let resp: generator::JsonResp = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp, RESP_SAVE_FAILED);
+
+    let flags = mock_state.fact_flags.as_ref().unwrap().read().unwrap();
+    assert_eq!(flags.len(), 1);
+}
+
+#[actix_rt::test]
+async fn storage_locked_while_in_use() {
+    let dir = make_dir();
+    let config = gen_config(&dir);
+
+    let held = storage::lock(&config).unwrap();
+    assert!(matches!(
+        storage::lock(&config),
+        Err(storage::StorageError::InUse)
+    ));
+
+    drop(held);
+    assert!(storage::lock(&config).is_ok());
+}
+
+fn gen_journal_config(dir: &tempdir::TempDir) -> Config {
+    let mut config = gen_config(dir);
+    config.storage.backend = StorageBackend::Journal;
+    config
+}
+
+#[actix_rt::test]
+async fn journal_needs_compact_interval() {
+    let dir = make_dir();
+    let mut config = gen_journal_config(&dir);
+    config.storage.compact_interval = Some(0);
+
+    assert!(matches!(
+        JournalStorage::open(&config),
+        Err(storage::StorageError::BadConfig(_))
+    ));
+}
+
+#[actix_rt::test]
+async fn journal_replays_and_compacts() {
+    let dir = make_dir();
+    let key = gen_admin_all_perms().key;
+
+    let mock_state = Data::new(gen_state_from(gen_journal_config(&dir)));
+    let app = test::init_service(
+        App::new()
+            .app_data(mock_state.clone())
+            .service(
+                web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+            )
+            .service(
+                web::resource("/admin/flag/{action}").route(web::post().to(admin::modify_flag)),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/delete")
+        .set_json(AdminFactRequest {
+            fact_id: Some(6682463169732688062),
+            ..gen_fact_request(Animal::from("cat"), key.clone())
+        })
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/admin/flag/delete")
+        .set_json(AdminFlagRequest {
+            key,
+            fact_id: None,
+            flag_id: Some(6682463169732628062),
+            reason: None,
+            fact_type: None,
+        })
+        .to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        Bytes::from_static(b"")
+    );
+
+    // Only the journal was written to, but the changes are replayed when loading
+    let journal = std::fs::read_to_string(dir.path().join("journal.jsonl")).unwrap();
+    assert_eq!(journal.lines().count(), 2);
+    assert!(!dir.path().join("cat_facts.trash.json").exists());
+
+    let cat = Animal::from("cat");
+    let replayed = gen_state_from(gen_journal_config(&dir));
+    assert_eq!(
+        replayed.fact_lists.get(&cat).unwrap().read().unwrap().len(),
+        2
+    );
+    assert!(replayed.fact_flags.unwrap().read().unwrap().is_empty());
+
+    JournalStorage::open(&gen_journal_config(&dir))
+        .unwrap()
+        .compact()
+        .unwrap();
+
+    let journal = std::fs::read_to_string(dir.path().join("journal.jsonl")).unwrap();
+    assert!(journal.is_empty());
+    let archive = std::fs::read_to_string(dir.path().join("journal.archive.jsonl")).unwrap();
+    assert_eq!(archive.lines().count(), 2);
+
+    // The snapshot now holds the changes by itself
+    let mut json_config = gen_config(&dir);
+    json_config.storage.backend = StorageBackend::Json;
+    let compacted = gen_state_from(json_config);
+    assert_eq!(
+        compacted
+            .fact_lists
+            .get(&cat)
+            .unwrap()
+            .read()
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(
+        compacted
+            .fact_lists
+            .get_trash(&cat)
+            .unwrap()
+            .read()
+            .unwrap()[0]
+            .fact
+            .id,
+        6682463169732688062
+    );
+    assert!(compacted.fact_flags.unwrap().read().unwrap().is_empty());
+}
+
+fn read_json(dir: &tempdir::TempDir, file: &str) -> serde_json::Value {
+    let contents = std::fs::read_to_string(dir.path().join(file)).unwrap();
+    serde_json::from_str(&contents).unwrap()
+}
+
+#[actix_rt::test]
+async fn bare_arrays_upgrade_when_saved() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_json_state(&dir));
+
+    // The example files are bare arrays, which are left alone until something changes
+    assert!(read_json(&dir, "cat_facts.json").is_array());
+    assert_eq!(
+        mock_state
+            .fact_lists
+            .get(&Animal::from("cat"))
+            .unwrap()
+            .read()
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let app =
+        test::init_service(App::new().app_data(mock_state.clone()).service(
+            web::resource("/admin/fact/{action}").route(web::post().to(admin::modify_fact)),
+        ))
+        .await;
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/delete")
+        .set_json(AdminFactRequest {
+            fact_id: Some(6682463169732688062),
+            ..gen_fact_request(Animal::from("cat"), gen_admin_all_perms().key)
+        })
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let facts = read_json(&dir, "cat_facts.json");
+    assert_eq!(facts["version"], storage::SCHEMA_VERSION);
+    assert_eq!(facts["facts"].as_array().unwrap().len(), 2);
+    let trash = read_json(&dir, "cat_facts.trash.json");
+    assert_eq!(trash["version"], storage::SCHEMA_VERSION);
+    assert_eq!(trash["trash"][0]["id"], 6682463169732688062u64);
+    assert!(read_json(&dir, "dog_facts.json").is_array());
+}
+
+#[actix_rt::test]
+async fn migrated_files_are_rewritten() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.storage.backend = StorageBackend::Json;
+    config.storage.rewrite_migrated = true;
+    let state = gen_state_from(config);
+
+    for (file, field) in [
+        ("cat_facts.json", "facts"),
+        ("dog_facts.json", "facts"),
+        ("fact_flags.json", "flags"),
+    ] {
+        let contents = read_json(&dir, file);
+        assert_eq!(contents["version"], storage::SCHEMA_VERSION);
+        assert!(contents[field].is_array());
+    }
+    assert_eq!(state.fact_flags.unwrap().read().unwrap().len(), 1);
+
+    // Loading them again gives the same facts back
+    let reloaded = gen_json_state(&dir);
+    assert_eq!(
+        reloaded
+            .fact_lists
+            .get(&Animal::from("dog"))
+            .unwrap()
+            .read()
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+#[actix_rt::test]
+async fn newer_files_are_refused() {
+    let dir = make_dir();
+    std::fs::write(
+        dir.path().join("cat_facts.json"),
+        r#"{"version": 99, "facts": []}"#,
+    )
+    .unwrap();
+
+    let storage = storage::JsonStorage::new(&dir.path().to_string_lossy());
+    let err = storage
+        .load_facts(&gen_animal_type("cat", "Cat"))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "stored data is schema version 99, but only up to version {} is supported",
+            storage::SCHEMA_VERSION
+        )
+    );
+}
@@ -0,0 +1,112 @@
+use actix_web::web::{self, Data};
+use actix_web::{test, App};
+
+use animal_api::animal_facts::{self, Fact};
+use animal_api::*;
+use std::collections::HashSet;
+mod generator;
+use crate::generator::*;
+
+fn gen_weight_request(fact_id: u64, weight: Option<f64>, key: String) -> AdminFactRequest {
+    AdminFactRequest {
+        fact_id: Some(fact_id),
+        fact_weight: weight,
+        ..gen_fact_request(Animal::from("cat"), key)
+    }
+}
+
+async fn set_weight(
+    state: &Data<APIState>,
+    request: AdminFactRequest,
+) -> actix_web::dev::ServiceResponse {
+    let app =
+        test::init_service(App::new().app_data(state.clone()).service(
+            web::resource("/admin/fact/weight").route(web::post().to(admin::modify_fact)),
+        ))
+        .await;
+
+    let req = test::TestRequest::post()
+        .uri("/admin/fact/weight")
+        .set_json(request)
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+// The IDs of several random cat facts
+async fn picked_ids(state: &Data<APIState>) -> HashSet<u64> {
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .service(web::resource("/{animal}/fact").route(web::get().to(animal_facts::get_fact))),
+    )
+    .await;
+
+    let mut ids = HashSet::new();
+    for _ in 0..30 {
+        let req = test::TestRequest::get().uri("/cat/fact").to_request();
+        let fact: Fact = test::call_and_read_body_json(&app, req).await;
+        ids.insert(fact.id);
+    }
+    ids
+}
+
+#[actix_rt::test]
+async fn missing_permission_weight() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    let request = gen_weight_request(6682463169732688062, Some(2.0), gen_admin_add_only().key);
+    let resp: generator::JsonResp =
+        test::read_body_json(set_weight(&mock_state, request).await).await;
+    assert_eq!(resp, RESP_MISSING_PERMS);
+}
+
+#[actix_rt::test]
+async fn invalid_weight() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    for weight in [None, Some(-1.0)] {
+        let request = gen_weight_request(6682463169732688062, weight, gen_admin_edit_only().key);
+        let resp: generator::JsonResp =
+            test::read_body_json(set_weight(&mock_state, request).await).await;
+        assert_eq!(resp, RESP_BAD_WEIGHT);
+    }
+}
+
+#[actix_rt::test]
+async fn zero_weight_never_picked() {
+    let dir = make_dir();
+    let mock_state = Data::new(gen_state(&dir));
+
+    for id in [6682463169732688062, 15889153292243741260] {
+        let request = gen_weight_request(id, Some(0.0), gen_admin_edit_only().key);
+        let resp = set_weight(&mock_state, request).await;
+        assert_eq!(resp.status(), 204);
+    }
+
+    assert_eq!(
+        picked_ids(&mock_state).await,
+        HashSet::from([15766631097752163384])
+    );
+
+    // The weight is saved along with the fact
+    let stored = mock_state
+        .storage
+        .load_facts(&gen_animal_type("cat", "Cat"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored[0].weight, Some(0.0));
+    assert_eq!(stored[2].weight, None);
+}
+
+#[actix_rt::test]
+async fn flagged_facts_weigh_less() {
+    let dir = make_dir();
+    let mut config = gen_config(&dir);
+    config.flagged_weight = 0.0;
+    let mock_state = Data::new(gen_state_from(config));
+
+    // The example flag is on this fact
+    assert!(!picked_ids(&mock_state).await.contains(&6682463169732688062));
+}